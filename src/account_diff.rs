@@ -0,0 +1,160 @@
+use {
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        pubkey::Pubkey,
+        transaction_context::TransactionAccount,
+    },
+    std::{collections::HashMap, fmt, ops::Range},
+};
+
+/// Changes a transaction made to a single account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub pubkey: Pubkey,
+    /// Post-simulation lamports minus pre-simulation lamports.
+    pub lamports_delta: i128,
+    /// `(pre, post)` owner, if it changed.
+    pub owner_change: Option<(Pubkey, Pubkey)>,
+    /// `(pre, post)` data length, if it changed.
+    pub data_len_change: Option<(usize, usize)>,
+    /// Byte ranges of the data that differ. Bytes past the end of the shorter
+    /// data count as changed.
+    pub changed_data_ranges: Vec<Range<usize>>,
+    /// `(pre, post)` executable flag, if it changed.
+    pub executable_change: Option<(bool, bool)>,
+    /// The account had no lamports before the transaction.
+    pub created: bool,
+    /// The account has no lamports after the transaction.
+    pub closed: bool,
+}
+
+impl AccountDiff {
+    /// Compares two states of the account `pubkey`. Accounts that don't exist are
+    /// represented by the default account.
+    pub fn new(pubkey: Pubkey, pre: &impl ReadableAccount, post: &impl ReadableAccount) -> Self {
+        Self {
+            pubkey,
+            lamports_delta: i128::from(post.lamports()) - i128::from(pre.lamports()),
+            owner_change: changed(*pre.owner(), *post.owner()),
+            data_len_change: changed(pre.data().len(), post.data().len()),
+            changed_data_ranges: changed_ranges(pre.data(), post.data()),
+            executable_change: changed(pre.executable(), post.executable()),
+            created: pre.lamports() == 0 && post.lamports() != 0,
+            closed: pre.lamports() != 0 && post.lamports() == 0,
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.lamports_delta == 0
+            && self.owner_change.is_none()
+            && self.data_len_change.is_none()
+            && self.changed_data_ranges.is_empty()
+            && self.executable_change.is_none()
+    }
+}
+
+impl fmt::Display for AccountDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.pubkey)?;
+        if self.created {
+            write!(f, " created,")?;
+        }
+        if self.closed {
+            write!(f, " closed,")?;
+        }
+        write!(f, " lamports {:+}", self.lamports_delta)?;
+        if let Some((pre, post)) = self.owner_change {
+            write!(f, ", owner {pre} -> {post}")?;
+        }
+        if let Some((pre, post)) = self.data_len_change {
+            write!(f, ", data length {pre} -> {post}")?;
+        }
+        if !self.changed_data_ranges.is_empty() {
+            let ranges = self
+                .changed_data_ranges
+                .iter()
+                .map(|range| format!("{}..{}", range.start, range.end))
+                .collect::<Vec<_>>();
+            write!(f, ", changed bytes [{}]", ranges.join(", "))?;
+        }
+        if let Some((pre, post)) = self.executable_change {
+            write!(f, ", executable {pre} -> {post}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Diffs every account in `post` that differs from its state in `pre`, in the
+/// order of `post`. Accounts missing from `pre` are compared to the default account.
+pub fn diff_accounts(pre: &[TransactionAccount], post: &[TransactionAccount]) -> Vec<AccountDiff> {
+    let pre = pre
+        .iter()
+        .map(|(pubkey, account)| (pubkey, account))
+        .collect::<HashMap<_, _>>();
+    post.iter()
+        .map(|(pubkey, post_account)| match pre.get(pubkey) {
+            Some(pre_account) => AccountDiff::new(*pubkey, *pre_account, post_account),
+            None => AccountDiff::new(*pubkey, &AccountSharedData::default(), post_account),
+        })
+        .filter(|diff| !diff.is_unchanged())
+        .collect()
+}
+
+fn changed<T: PartialEq>(pre: T, post: T) -> Option<(T, T)> {
+    (pre != post).then_some((pre, post))
+}
+
+fn changed_ranges(pre: &[u8], post: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    let mut push = |index: usize| match ranges.last_mut() {
+        Some(last) if last.end == index => last.end = index + 1,
+        _ => ranges.push(index..index + 1),
+    };
+    for (index, (pre_byte, post_byte)) in pre.iter().zip(post).enumerate() {
+        if pre_byte != post_byte {
+            push(index);
+        }
+    }
+    let common_len = pre.len().min(post.len());
+    let max_len = pre.len().max(post.len());
+    if max_len > common_len {
+        match ranges.last_mut() {
+            Some(last) if last.end == common_len => last.end = max_len,
+            _ => ranges.push(common_len..max_len),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_ranges_equal() {
+        assert!(changed_ranges(&[], &[]).is_empty());
+        assert!(changed_ranges(&[1, 2, 3], &[1, 2, 3]).is_empty());
+    }
+
+    #[test]
+    fn test_changed_ranges_merges_adjacent_bytes() {
+        assert_eq!(
+            changed_ranges(&[0, 0, 0, 0, 0, 0], &[1, 1, 0, 1, 0, 1]),
+            vec![0..2, 3..4, 5..6]
+        );
+    }
+
+    #[test]
+    fn test_changed_ranges_length_change() {
+        // Grown data.
+        assert_eq!(changed_ranges(&[1, 2], &[1, 2, 3, 4]), vec![2..4]);
+        // Shrunk data.
+        assert_eq!(changed_ranges(&[1, 2, 3, 4], &[1]), vec![1..4]);
+        // A change at the end of the common bytes extends into the tail.
+        assert_eq!(changed_ranges(&[1, 2], &[1, 3, 4]), vec![1..3]);
+        assert_eq!(changed_ranges(&[1, 2], &[3, 2, 4]), vec![0..1, 2..3]);
+        // Created and closed accounts.
+        assert_eq!(changed_ranges(&[], &[0, 0]), vec![0..2]);
+        assert_eq!(changed_ranges(&[7, 7, 7], &[]), vec![0..3]);
+    }
+}
@@ -0,0 +1,315 @@
+use {
+    solana_sdk::pubkey::Pubkey,
+    std::{collections::HashMap, str::FromStr},
+};
+
+/// Compute units consumed by a single instruction invocation, including the
+/// invocations it made through CPI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionComputeUnits {
+    pub program_id: Pubkey,
+    pub stack_height: usize,
+    /// Units reported by the runtime for this invocation, CPIs included.
+    /// Builtin programs don't log their consumption: for a top-level builtin
+    /// this is derived from the remaining budget logged by its neighbours, or
+    /// from the transaction's total when it is the only instruction left
+    /// unknown. It is `None` for builtins invoked through CPI, whose usage
+    /// can't be told apart from their caller's, and for consecutive top-level
+    /// builtins, of which only the sum is known.
+    pub units_consumed: Option<u64>,
+    pub inner_instructions: Vec<InstructionComputeUnits>,
+}
+
+impl InstructionComputeUnits {
+    /// Units consumed by this invocation alone, excluding its CPIs.
+    pub fn exclusive_units(&self) -> Option<u64> {
+        let inner = self
+            .inner_instructions
+            .iter()
+            .filter_map(|ix| ix.units_consumed)
+            .sum::<u64>();
+        self.units_consumed.map(|units| units.saturating_sub(inner))
+    }
+
+    fn accumulate_by_program(&self, totals: &mut HashMap<Pubkey, u64>) {
+        if let Some(units) = self.exclusive_units() {
+            *totals.entry(self.program_id).or_default() += units;
+        }
+        for ix in &self.inner_instructions {
+            ix.accumulate_by_program(totals);
+        }
+    }
+}
+
+/// Sums the exclusive units of every invocation per program.
+pub fn units_by_program(instructions: &[InstructionComputeUnits]) -> HashMap<Pubkey, u64> {
+    let mut totals = HashMap::new();
+    for ix in instructions {
+        ix.accumulate_by_program(&mut totals);
+    }
+    totals
+}
+
+/// Rebuilds the invocation tree from the runtime's `invoke`, `consumed` and
+/// `success`/`failed` log lines. One root is returned per top-level instruction
+/// that was reached before execution stopped. `units_consumed` is the
+/// transaction's total.
+pub fn parse_compute_units(logs: &[String], units_consumed: u64) -> Vec<InstructionComputeUnits> {
+    let mut roots = vec![];
+    // The budget remaining when each top-level instruction started, from the
+    // `consumed X of Y` line of the instructions that log one.
+    let mut root_budgets = vec![];
+    let mut stack: Vec<(InstructionComputeUnits, Option<u64>)> = vec![];
+
+    for log in logs {
+        let Some(rest) = log.strip_prefix("Program ") else {
+            continue;
+        };
+        let Some((program, rest)) = rest.split_once(' ') else {
+            continue;
+        };
+        let Ok(program_id) = Pubkey::from_str(program) else {
+            continue;
+        };
+
+        if let Some(height) = rest
+            .strip_prefix("invoke [")
+            .and_then(|height| height.strip_suffix(']'))
+        {
+            stack.push((
+                InstructionComputeUnits {
+                    program_id,
+                    stack_height: height.parse().unwrap_or(stack.len() + 1),
+                    units_consumed: None,
+                    inner_instructions: vec![],
+                },
+                None,
+            ));
+        } else if let Some(consumed) = rest.strip_prefix("consumed ") {
+            // "consumed X of Y compute units"
+            let mut words = consumed.split(' ');
+            let units = words.next().and_then(|units| units.parse::<u64>().ok());
+            let budget = words.nth(1).and_then(|budget| budget.parse::<u64>().ok());
+            if let Some((current, current_budget)) = stack.last_mut() {
+                if current.program_id == program_id {
+                    current.units_consumed = units;
+                    *current_budget = budget;
+                }
+            }
+        } else if rest == "success" || rest.starts_with("failed") {
+            if let Some(current) = stack.pop() {
+                attach(&mut roots, &mut root_budgets, &mut stack, current);
+            }
+        }
+    }
+
+    // Invocations left open when the logs end (e.g. truncated logs).
+    let truncated = !stack.is_empty() || logs.last().is_some_and(|log| log == "Log truncated");
+    while let Some(current) = stack.pop() {
+        attach(&mut roots, &mut root_budgets, &mut stack, current);
+    }
+
+    derive_builtin_units(
+        &mut roots,
+        &root_budgets,
+        (!truncated).then_some(units_consumed),
+    );
+    roots
+}
+
+fn attach(
+    roots: &mut Vec<InstructionComputeUnits>,
+    root_budgets: &mut Vec<Option<u64>>,
+    stack: &mut [(InstructionComputeUnits, Option<u64>)],
+    (instruction, budget): (InstructionComputeUnits, Option<u64>),
+) {
+    match stack.last_mut() {
+        Some((parent, _)) => parent.inner_instructions.push(instruction),
+        None => {
+            roots.push(instruction);
+            root_budgets.push(budget);
+        }
+    }
+}
+
+/// Fills in the units of top-level builtins. Nothing runs between top-level
+/// instructions, so a builtin consumed the drop in the remaining budget between
+/// its neighbours. Once a single instruction is left unknown, it consumed what
+/// the others didn't of `units_consumed`, which is unknown for truncated logs.
+fn derive_builtin_units(
+    roots: &mut [InstructionComputeUnits],
+    budgets: &[Option<u64>],
+    units_consumed: Option<u64>,
+) {
+    let remaining_after = |index: usize, roots: &[InstructionComputeUnits]| {
+        budgets[index]?.checked_sub(roots[index].units_consumed?)
+    };
+    for index in 1..roots.len().saturating_sub(1) {
+        if roots[index].units_consumed.is_none() {
+            roots[index].units_consumed = remaining_after(index - 1, roots)
+                .zip(budgets[index + 1])
+                .and_then(|(before, after)| before.checked_sub(after));
+        }
+    }
+
+    let unknown = (0..roots.len())
+        .filter(|&index| roots[index].units_consumed.is_none())
+        .collect::<Vec<_>>();
+    if let ([index], Some(units_consumed)) = (unknown.as_slice(), units_consumed) {
+        let known = roots.iter().filter_map(|ix| ix.units_consumed).sum::<u64>();
+        roots[*index].units_consumed = units_consumed.checked_sub(known);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::system_program};
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_nested_cpi() {
+        let outer = Pubkey::new_unique();
+        let inner = Pubkey::new_unique();
+        let instructions = parse_compute_units(
+            &logs(&[
+                &format!("Program {outer} invoke [1]"),
+                "Program log: Instruction: Transfer",
+                &format!("Program {inner} invoke [2]"),
+                &format!("Program {inner} consumed 1200 of 190000 compute units"),
+                &format!("Program {inner} success"),
+                &format!("Program {system} invoke [2]", system = system_program::id()),
+                &format!("Program {system} success", system = system_program::id()),
+                &format!("Program {outer} consumed 5000 of 200000 compute units"),
+                &format!("Program {outer} success"),
+            ]),
+            5000,
+        );
+
+        assert_eq!(instructions.len(), 1);
+        let root = &instructions[0];
+        assert_eq!(root.program_id, outer);
+        assert_eq!(root.stack_height, 1);
+        assert_eq!(root.units_consumed, Some(5000));
+        assert_eq!(root.exclusive_units(), Some(3800));
+        assert_eq!(root.inner_instructions.len(), 2);
+        assert_eq!(root.inner_instructions[0].program_id, inner);
+        assert_eq!(root.inner_instructions[0].stack_height, 2);
+        assert_eq!(root.inner_instructions[0].units_consumed, Some(1200));
+        // The usage of builtins invoked through CPI is unknown.
+        assert_eq!(root.inner_instructions[1].program_id, system_program::id());
+        assert_eq!(root.inner_instructions[1].units_consumed, None);
+        assert_eq!(
+            units_by_program(&instructions),
+            HashMap::from([(outer, 3800), (inner, 1200)])
+        );
+    }
+
+    #[test]
+    fn test_parse_failed_invoke() {
+        let program_id = Pubkey::new_unique();
+        let instructions = parse_compute_units(
+            &logs(&[
+                &format!("Program {program_id} invoke [1]"),
+                &format!("Program {program_id} consumed 300 of 200000 compute units"),
+                &format!("Program {program_id} failed: custom program error: 0x1"),
+            ]),
+            300,
+        );
+
+        assert_eq!(
+            instructions,
+            vec![InstructionComputeUnits {
+                program_id,
+                stack_height: 1,
+                units_consumed: Some(300),
+                inner_instructions: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated_logs() {
+        let outer = Pubkey::new_unique();
+        let inner = Pubkey::new_unique();
+        let instructions = parse_compute_units(
+            &logs(&[
+                &format!("Program {outer} invoke [1]"),
+                &format!("Program {inner} invoke [2]"),
+                "Log truncated",
+            ]),
+            1000,
+        );
+
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].program_id, outer);
+        assert_eq!(instructions[0].units_consumed, None);
+        assert_eq!(instructions[0].inner_instructions.len(), 1);
+        assert_eq!(instructions[0].inner_instructions[0].program_id, inner);
+        assert!(units_by_program(&instructions).is_empty());
+    }
+
+    #[test]
+    fn test_parse_top_level_builtins() {
+        let program_id = Pubkey::new_unique();
+        let system = system_program::id();
+        let instructions = parse_compute_units(
+            &logs(&[
+                &format!("Program {system} invoke [1]"),
+                &format!("Program {system} success"),
+                &format!("Program {program_id} invoke [1]"),
+                &format!("Program {program_id} consumed 1000 of 199850 compute units"),
+                &format!("Program {program_id} success"),
+                &format!("Program {system} invoke [1]"),
+                &format!("Program {system} success"),
+                &format!("Program {program_id} invoke [1]"),
+                &format!("Program {program_id} consumed 2000 of 198700 compute units"),
+                &format!("Program {program_id} success"),
+            ]),
+            3300,
+        );
+
+        assert_eq!(
+            instructions
+                .iter()
+                .map(|ix| ix.units_consumed)
+                .collect::<Vec<_>>(),
+            vec![Some(150), Some(1000), Some(150), Some(2000)]
+        );
+        assert_eq!(
+            units_by_program(&instructions),
+            HashMap::from([(system, 300), (program_id, 3000)])
+        );
+    }
+
+    #[test]
+    fn test_parse_consecutive_top_level_builtins() {
+        let system = system_program::id();
+        let instructions = parse_compute_units(
+            &logs(&[
+                &format!("Program {system} invoke [1]"),
+                &format!("Program {system} success"),
+                &format!("Program {system} invoke [1]"),
+                &format!("Program {system} success"),
+            ]),
+            300,
+        );
+
+        assert_eq!(instructions.len(), 2);
+        assert!(instructions
+            .iter()
+            .all(|ix| ix.program_id == system && ix.units_consumed.is_none()));
+        assert_eq!(instructions[1].exclusive_units(), None);
+
+        let instructions = parse_compute_units(
+            &logs(&[
+                &format!("Program {system} invoke [1]"),
+                &format!("Program {system} success"),
+            ]),
+            150,
+        );
+        assert_eq!(instructions[0].units_consumed, Some(150));
+    }
+}
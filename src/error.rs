@@ -0,0 +1,56 @@
+use std::{fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum SimulatorError {
+    /// The accounts file could not be read.
+    ReadAccounts { path: PathBuf, source: io::Error },
+    /// The accounts file is not valid JSON or lacks the top-level `accounts` array.
+    ParseAccounts(String),
+    /// An account entry is missing a required field.
+    MissingField { pubkey: String, field: &'static str },
+    /// An account entry has a field that could not be decoded.
+    InvalidField {
+        pubkey: String,
+        field: &'static str,
+        reason: String,
+    },
+    /// The simulator configuration is inconsistent.
+    InvalidConfig(String),
+    /// The program runtime environment could not be created.
+    RuntimeEnvironment(String),
+    /// An RPC request's parameters are invalid.
+    InvalidRpcParams(String),
+}
+
+impl fmt::Display for SimulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadAccounts { path, source } => {
+                write!(f, "failed to read accounts file {}: {source}", path.display())
+            }
+            Self::ParseAccounts(reason) => write!(f, "failed to parse accounts file: {reason}"),
+            Self::MissingField { pubkey, field } => {
+                write!(f, "account {pubkey} is missing field `{field}`")
+            }
+            Self::InvalidField {
+                pubkey,
+                field,
+                reason,
+            } => write!(f, "account {pubkey} has invalid field `{field}`: {reason}"),
+            Self::InvalidConfig(reason) => write!(f, "invalid simulator configuration: {reason}"),
+            Self::RuntimeEnvironment(reason) => {
+                write!(f, "failed to create program runtime environment: {reason}")
+            }
+            Self::InvalidRpcParams(reason) => write!(f, "invalid RPC params: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SimulatorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadAccounts { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
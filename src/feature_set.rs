@@ -0,0 +1,108 @@
+use {
+    solana_sdk::{
+        account::AccountSharedData,
+        feature::{self, Feature},
+        feature_set::{self, FeatureSet},
+        pubkey::Pubkey,
+    },
+    std::collections::HashMap,
+};
+
+/// Selects the runtime features that are active during simulation.
+#[derive(Debug, Default, Clone)]
+pub enum FeatureSetConfig {
+    /// Every feature known to the runtime is active.
+    #[default]
+    AllEnabled,
+    /// The features active on a cluster, see [`Cluster`].
+    Cluster(Cluster),
+    /// Only the listed features are active.
+    Active(Vec<Pubkey>),
+    /// Every feature is active except the listed ones.
+    AllEnabledExcept(Vec<Pubkey>),
+    /// Like a bank: only the features whose account in the loaded accounts has
+    /// been activated are active.
+    FromAccounts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cluster {
+    MainnetBeta,
+}
+
+impl Cluster {
+    /// Features of this runtime version that were still pending activation on the
+    /// cluster when this crate was pinned. Use [`FeatureSetConfig::FromAccounts`]
+    /// with fetched feature accounts to match a cluster exactly.
+    pub fn inactive_features(&self) -> Vec<Pubkey> {
+        match self {
+            Self::MainnetBeta => vec![
+                feature_set::enable_loader_v4::id(),
+                feature_set::disable_new_loader_v3_deployments::id(),
+                feature_set::disable_sbpf_v0_execution::id(),
+                feature_set::reenable_sbpf_v0_execution::id(),
+                feature_set::enable_sbpf_v1_deployment_and_execution::id(),
+                feature_set::enable_sbpf_v2_deployment_and_execution::id(),
+                feature_set::enable_sbpf_v3_deployment_and_execution::id(),
+                feature_set::bpf_account_data_direct_mapping::id(),
+                feature_set::remove_accounts_executable_flag_checks::id(),
+                feature_set::lift_cpi_caller_restriction::id(),
+                feature_set::disable_account_loader_special_case::id(),
+                feature_set::accounts_lt_hash::id(),
+                feature_set::snapshots_lt_hash::id(),
+                feature_set::remove_accounts_delta_hash::id(),
+                feature_set::enable_secp256r1_precompile::id(),
+                feature_set::migrate_stake_program_to_core_bpf::id(),
+                feature_set::migrate_address_lookup_table_program_to_core_bpf::id(),
+                feature_set::deplete_cu_meter_on_vm_failure::id(),
+                feature_set::reserve_minimal_cus_for_builtin_instructions::id(),
+                feature_set::raise_block_limits_to_50m::id(),
+                feature_set::fix_alt_bn128_multiplication_input_length::id(),
+                feature_set::drop_unchained_merkle_shreds::id(),
+                feature_set::enable_turbine_extended_fanout_experiments::id(),
+                feature_set::zk_elgamal_proof_program_enabled::id(),
+                feature_set::update_hashes_per_tick3::id(),
+                feature_set::update_hashes_per_tick4::id(),
+                feature_set::update_hashes_per_tick5::id(),
+                feature_set::update_hashes_per_tick6::id(),
+            ],
+        }
+    }
+}
+
+impl FeatureSetConfig {
+    pub fn build(&self, accounts: &HashMap<Pubkey, AccountSharedData>) -> FeatureSet {
+        match self {
+            Self::AllEnabled => FeatureSet::all_enabled(),
+            Self::Cluster(cluster) => all_enabled_except(&cluster.inactive_features()),
+            Self::Active(active) => {
+                let mut feature_set = FeatureSet::default();
+                for feature_id in active {
+                    feature_set.activate(feature_id, 0);
+                }
+                feature_set
+            }
+            Self::AllEnabledExcept(inactive) => all_enabled_except(inactive),
+            Self::FromAccounts => {
+                let mut feature_set = FeatureSet::default();
+                for (feature_id, account) in accounts {
+                    if let Some(Feature {
+                        activated_at: Some(slot),
+                    }) = feature::from_account(account)
+                    {
+                        feature_set.activate(feature_id, slot);
+                    }
+                }
+                feature_set
+            }
+        }
+    }
+}
+
+fn all_enabled_except(inactive: &[Pubkey]) -> FeatureSet {
+    let mut feature_set = FeatureSet::all_enabled();
+    for feature_id in inactive {
+        feature_set.deactivate(feature_id);
+    }
+    feature_set
+}
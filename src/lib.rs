@@ -1,7 +1,23 @@
+mod account_diff;
+mod compute_units;
+mod error;
+mod feature_set;
+mod program;
+mod rpc_response;
+mod session;
 mod simulator;
+mod token_balances;
 
+pub use account_diff::{diff_accounts, AccountDiff};
+pub use compute_units::InstructionComputeUnits;
+pub use error::SimulatorError;
+pub use feature_set::{Cluster, FeatureSetConfig};
+pub use program::{ProgramDiagnostic, UpgradeableProgram};
+pub use session::{BundleSimulationResult, SimulatorSession};
 pub use simulator::{
+    ClockConfig,
     Simulator,
     SimulatorConfig,
     TransactionSimulationResult,
-};
\ No newline at end of file
+};
+pub use token_balances::TransactionTokenBalance;
\ No newline at end of file
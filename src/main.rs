@@ -4,7 +4,6 @@ use {
         instruction::{AccountMeta, Instruction},
         message::Message,
         pubkey::Pubkey,
-        signature::Signer,
         transaction::{MessageHash, SanitizedTransaction, Transaction},
     },
     solana_simulate::{Simulator, SimulatorConfig},
@@ -18,9 +17,16 @@ use {
 fn main() {
     let config = SimulatorConfig {
         accounts_path: PathBuf::from("./accounts.json"),
+        ..Default::default()
     };
 
-    let simulator = Simulator::new(config);
+    let simulator = match Simulator::try_new(config) {
+        Ok(simulator) => simulator,
+        Err(err) => {
+            eprintln!("Failed to create simulator: {err}");
+            std::process::exit(1);
+        }
+    };
 
     let signer = Pubkey::from_str("H7GCUaJMUgdQiNYyoQTTmwG4fSYMV8W8ECmATZ2kyNTJ").unwrap();
 
@@ -113,4 +119,21 @@ fn main() {
     }
 
     println!("Simulation result: {:?}", simulation_result.result);
+    for diagnostic in &simulation_result.program_diagnostics {
+        println!("{}", diagnostic);
+    }
+    println!("Units consumed: {}", simulation_result.units_consumed);
+    println!("Fee: {}", simulation_result.fee);
+
+    println!("Account changes:");
+    for diff in simulation_result.account_diffs() {
+        println!("{}", diff);
+    }
+
+    for (index, instruction) in simulation_result.compute_units.iter().enumerate() {
+        println!(
+            "Instruction #{} {}: {:?} compute units",
+            index, instruction.program_id, instruction.units_consumed
+        );
+    }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use {
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        account_utils::StateMut,
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        clock::Slot,
+        pubkey::Pubkey,
+    },
+    std::fmt,
+};
+
+/// Deployment details of a program owned by the upgradeable BPF loader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeableProgram {
+    pub programdata_address: Pubkey,
+    /// Slot the program was last deployed or upgraded at.
+    pub deployment_slot: Slot,
+    /// `None` once the program has been made immutable.
+    pub upgrade_authority: Option<Pubkey>,
+}
+
+/// Explains why a program could not be loaded. Transactions invoking it fail
+/// the same way they would on a validator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramDiagnostic {
+    pub program_id: Pubkey,
+    pub reason: String,
+}
+
+impl fmt::Display for ProgramDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "program {} can't be executed: {}",
+            self.program_id, self.reason
+        )
+    }
+}
+
+impl std::error::Error for ProgramDiagnostic {}
+
+/// Reads the programdata account of the upgradeable program `program_account`,
+/// returning it along with the program's deployment details.
+pub(crate) fn load_upgradeable_program(
+    program_id: &Pubkey,
+    program_account: &AccountSharedData,
+    get_account: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> Result<(UpgradeableProgram, AccountSharedData), ProgramDiagnostic> {
+    let diagnostic = |reason: String| ProgramDiagnostic {
+        program_id: *program_id,
+        reason,
+    };
+    if !bpf_loader_upgradeable::check_id(program_account.owner()) {
+        return Err(diagnostic(
+            "not owned by the upgradeable BPF loader".to_string(),
+        ));
+    }
+    let Ok(UpgradeableLoaderState::Program {
+        programdata_address,
+    }) = program_account.state()
+    else {
+        return Err(diagnostic("invalid program account data".to_string()));
+    };
+
+    let programdata_account = get_account(&programdata_address).ok_or_else(|| {
+        diagnostic(format!(
+            "programdata account {programdata_address} not found"
+        ))
+    })?;
+    if !bpf_loader_upgradeable::check_id(programdata_account.owner()) {
+        return Err(diagnostic(format!(
+            "programdata account {programdata_address} is not owned by the upgradeable BPF loader"
+        )));
+    }
+    let Ok(UpgradeableLoaderState::ProgramData {
+        slot,
+        upgrade_authority_address,
+    }) = programdata_account.state()
+    else {
+        return Err(diagnostic(format!(
+            "programdata account {programdata_address} is closed"
+        )));
+    };
+
+    Ok((
+        UpgradeableProgram {
+            programdata_address,
+            deployment_slot: slot,
+            upgrade_authority: upgrade_authority_address,
+        },
+        programdata_account,
+    ))
+}
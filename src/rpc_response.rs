@@ -0,0 +1,176 @@
+use {
+    crate::{
+        error::SimulatorError, simulator::TransactionSimulationResult,
+        token_balances::mint_additional_data,
+    },
+    solana_account_decoder::{
+        encode_ui_account,
+        parse_account_data::AccountAdditionalDataV3,
+        parse_token::{get_token_account_mint, is_known_spl_token_id},
+        UiAccount, UiAccountEncoding, UiDataSliceConfig, MAX_BASE58_BYTES,
+    },
+    solana_rpc_client_api::{
+        config::{RpcAccountInfoConfig, RpcSimulateTransactionConfig},
+        response::RpcSimulateTransactionResult,
+    },
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        clock::UnixTimestamp,
+        message::AccountKeys,
+        pubkey::Pubkey,
+    },
+    solana_transaction_status::{map_inner_instructions, parse_ui_inner_instructions},
+    std::collections::HashMap,
+};
+
+/// Builds the `simulateTransaction` response for `result`. Requested accounts are
+/// read from the simulation's post-execution accounts, then through `get_account`.
+pub(crate) fn simulation_response(
+    result: &TransactionSimulationResult,
+    account_keys: &AccountKeys,
+    config: &RpcSimulateTransactionConfig,
+    unix_timestamp: UnixTimestamp,
+    get_account: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> Result<RpcSimulateTransactionResult, SimulatorError> {
+    let accounts = match &config.accounts {
+        Some(accounts_config) => {
+            let encoding = accounts_config
+                .encoding
+                .unwrap_or(UiAccountEncoding::Base64);
+            if matches!(
+                encoding,
+                UiAccountEncoding::Binary | UiAccountEncoding::Base58
+            ) {
+                return Err(SimulatorError::InvalidRpcParams(
+                    "base58 encoding not supported".to_string(),
+                ));
+            }
+            if accounts_config.addresses.len() > account_keys.len() {
+                return Err(SimulatorError::InvalidRpcParams(format!(
+                    "Too many accounts provided; max {}",
+                    account_keys.len()
+                )));
+            }
+            if result.result.is_err() {
+                Some(vec![None; accounts_config.addresses.len()])
+            } else {
+                let post_simulation_accounts = result
+                    .post_simulation_accounts
+                    .iter()
+                    .map(|(pubkey, account)| (pubkey, account))
+                    .collect::<HashMap<_, _>>();
+                let get_account = |pubkey: &Pubkey| match post_simulation_accounts.get(pubkey) {
+                    Some(account) => Some((*account).clone()),
+                    None => get_account(pubkey),
+                };
+                let accounts = accounts_config
+                    .addresses
+                    .iter()
+                    .map(|address| {
+                        let pubkey = address.parse::<Pubkey>().map_err(|err| {
+                            SimulatorError::InvalidRpcParams(format!("Invalid param: {err:?}"))
+                        })?;
+                        Ok(get_account(&pubkey).map(|account| {
+                            encode_account(
+                                &pubkey,
+                                &account,
+                                encoding,
+                                None,
+                                unix_timestamp,
+                                get_account,
+                            )
+                        }))
+                    })
+                    .collect::<Result<Vec<_>, SimulatorError>>()?;
+                Some(accounts)
+            }
+        }
+        None => None,
+    };
+
+    let inner_instructions = config
+        .inner_instructions
+        .then(|| result.inner_instructions.clone())
+        .flatten()
+        .map(|inner_instructions| {
+            map_inner_instructions(inner_instructions)
+                .map(|instructions| parse_ui_inner_instructions(instructions, account_keys))
+                .collect()
+        });
+
+    Ok(RpcSimulateTransactionResult {
+        err: result.result.clone().err(),
+        logs: Some(result.logs.clone()),
+        accounts,
+        units_consumed: Some(result.units_consumed),
+        return_data: result.return_data.clone().map(Into::into),
+        inner_instructions,
+        replacement_blockhash: None,
+    })
+}
+
+/// Encodes the account `pubkey` like `getAccountInfo` does, reading it and the mint
+/// of JSON-parsed token accounts through `get_account`.
+pub(crate) fn account_info(
+    pubkey: &Pubkey,
+    config: &RpcAccountInfoConfig,
+    unix_timestamp: UnixTimestamp,
+    get_account: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> Result<Option<UiAccount>, SimulatorError> {
+    let encoding = config.encoding.unwrap_or(UiAccountEncoding::Binary);
+    if encoding == UiAccountEncoding::JsonParsed && config.data_slice.is_some() {
+        return Err(SimulatorError::InvalidRpcParams(
+            "Sliced account data can only be encoded using binary (base 58) or base64 encoding."
+                .to_string(),
+        ));
+    }
+    // The bank doesn't store accounts without lamports.
+    let Some(account) = get_account(pubkey).filter(|account| account.lamports() != 0) else {
+        return Ok(None);
+    };
+    let data_len = match config.data_slice {
+        Some(slice) => slice
+            .length
+            .min(account.data().len().saturating_sub(slice.offset)),
+        None => account.data().len(),
+    };
+    if matches!(
+        encoding,
+        UiAccountEncoding::Binary | UiAccountEncoding::Base58
+    ) && data_len > MAX_BASE58_BYTES
+    {
+        return Err(SimulatorError::InvalidRpcParams(format!(
+            "Encoded binary (base 58) data should be less than {MAX_BASE58_BYTES} bytes, please use Base64 encoding."
+        )));
+    }
+    Ok(Some(encode_account(
+        pubkey,
+        &account,
+        encoding,
+        config.data_slice,
+        unix_timestamp,
+        get_account,
+    )))
+}
+
+/// Encodes `account` like the RPC does. JSON-parsed token accounts read their mint
+/// through `get_account` to report UI amounts.
+fn encode_account(
+    pubkey: &Pubkey,
+    account: &AccountSharedData,
+    encoding: UiAccountEncoding,
+    data_slice: Option<UiDataSliceConfig>,
+    unix_timestamp: UnixTimestamp,
+    get_account: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> UiAccount {
+    let additional_data = (encoding == UiAccountEncoding::JsonParsed
+        && is_known_spl_token_id(account.owner()))
+    .then(|| get_token_account_mint(account.data()))
+    .flatten()
+    .and_then(|mint| get_account(&mint))
+    .and_then(|mint| mint_additional_data(mint.data(), unix_timestamp))
+    .map(|data| AccountAdditionalDataV3 {
+        spl_token_additional_data: Some(data),
+    });
+    encode_ui_account(pubkey, account, encoding, additional_data, data_slice)
+}
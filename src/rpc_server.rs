@@ -0,0 +1,528 @@
+use {
+    axum::{extract::State, routing::post, Json, Router},
+    base64::{prelude::BASE64_STANDARD, Engine},
+    bincode::Options,
+    serde::{de::DeserializeOwned, Deserialize},
+    serde_json::{json, Value},
+    solana_rpc_client_api::{
+        config::{RpcAccountInfoConfig, RpcContextConfig, RpcSimulateTransactionConfig},
+        custom_error::{
+            JSON_RPC_SERVER_ERROR_TRANSACTION_PRECOMPILE_VERIFICATION_FAILURE,
+            JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE,
+        },
+        request::MAX_MULTIPLE_ACCOUNTS,
+        response::{Response, RpcBlockhash, RpcResponseContext},
+    },
+    solana_sdk::{
+        account::ReadableAccount,
+        bs58,
+        clock::MAX_PROCESSING_AGE,
+        feature_set::move_precompile_verification_to_svm,
+        packet::PACKET_DATA_SIZE,
+        pubkey::Pubkey,
+        transaction::{MessageHash, SanitizedTransaction, VersionedTransaction},
+    },
+    solana_simulate::{Simulator, SimulatorConfig, SimulatorError},
+    solana_transaction_status::UiTransactionEncoding,
+    std::{collections::HashSet, net::SocketAddr, path::PathBuf, sync::Arc},
+    tower_http::cors::CorsLayer,
+};
+
+const DEFAULT_ACCOUNTS_PATH: &str = "./accounts.json";
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:8899";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Option<Vec<Value>>,
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<SimulatorError> for RpcError {
+    fn from(err: SimulatorError) -> Self {
+        match err {
+            SimulatorError::InvalidRpcParams(message) => Self::invalid_params(message),
+            err => Self {
+                code: INTERNAL_ERROR,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+/// Serves the simulator's accounts over JSON-RPC.
+///
+/// Usage: `solana-simulate-rpc [ACCOUNTS_PATH] [BIND_ADDRESS]`
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let accounts_path = args
+        .next()
+        .unwrap_or_else(|| DEFAULT_ACCOUNTS_PATH.to_string());
+    let address: SocketAddr = args
+        .next()
+        .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_string())
+        .parse()?;
+
+    let config = SimulatorConfig {
+        accounts_path: PathBuf::from(accounts_path),
+        ..Default::default()
+    };
+    let simulator = match Simulator::try_new(config) {
+        Ok(simulator) => Arc::new(simulator),
+        Err(err) => {
+            eprintln!("Failed to create simulator: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let app = Router::new()
+        .route("/", post(handle))
+        .layer(CorsLayer::permissive())
+        .with_state(simulator);
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    println!("Serving JSON-RPC on http://{address}");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn handle(State(simulator): State<Arc<Simulator>>, body: String) -> Json<Value> {
+    // Simulations are CPU bound, so keep them off the async workers.
+    let response = tokio::task::spawn_blocking(move || handle_body(&simulator, &body))
+        .await
+        .unwrap_or_else(|err| error_response(Value::Null, INTERNAL_ERROR, &err.to_string()));
+    Json(response)
+}
+
+fn handle_body(simulator: &Simulator, body: &str) -> Value {
+    match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(requests)) if !requests.is_empty() => Value::Array(
+            requests
+                .into_iter()
+                .map(|request| handle_request(simulator, request))
+                .collect(),
+        ),
+        Ok(Value::Array(_)) => error_response(Value::Null, INVALID_REQUEST, "Invalid request"),
+        Ok(request) => handle_request(simulator, request),
+        Err(_) => error_response(Value::Null, PARSE_ERROR, "Parse error"),
+    }
+}
+
+fn handle_request(simulator: &Simulator, request: Value) -> Value {
+    let request = match serde_json::from_value::<RpcRequest>(request) {
+        Ok(request) => request,
+        Err(_) => return error_response(Value::Null, INVALID_REQUEST, "Invalid request"),
+    };
+    let params = request.params.unwrap_or_default();
+    let result = match request.method.as_str() {
+        "simulateTransaction" => simulate_transaction(simulator, &params),
+        "getAccountInfo" => get_account_info(simulator, &params),
+        "getMultipleAccounts" => get_multiple_accounts(simulator, &params),
+        "getBalance" => get_balance(simulator, &params),
+        "getLatestBlockhash" => get_latest_blockhash(simulator, &params),
+        "getSlot" => get_slot(simulator, &params),
+        _ => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: "Method not found".to_string(),
+        }),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": request.id }),
+        Err(err) => error_response(request.id, err.code, &err.message),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code, "message": message },
+        "id": id,
+    })
+}
+
+fn simulate_transaction(simulator: &Simulator, params: &[Value]) -> Result<Value, RpcError> {
+    let data: String = required_param(params, 0)?;
+    let config: RpcSimulateTransactionConfig = optional_param(params, 1)?;
+    if config.sig_verify && config.replace_recent_blockhash {
+        return Err(RpcError::invalid_params(
+            "sigVerify may not be used with replaceRecentBlockhash",
+        ));
+    }
+    let transaction = decode_transaction(&data, config.encoding)?;
+    let transaction = SanitizedTransaction::try_create(
+        transaction,
+        MessageHash::Compute,
+        None,
+        simulator.clone(),
+        &HashSet::new(),
+    )
+    .map_err(|err| RpcError::invalid_params(format!("invalid transaction: {err}")))?;
+
+    let transaction = if config.replace_recent_blockhash {
+        simulator
+            .replace_recent_blockhash(&transaction)
+            .map_err(|err| RpcError::invalid_params(format!("invalid transaction: {err}")))?
+    } else {
+        if config.sig_verify {
+            verify_transaction(simulator, &transaction)?;
+        }
+        transaction
+    };
+
+    let result = simulator.simulate_transaction_unchecked(&transaction, config.inner_instructions);
+    let mut value = simulator.rpc_simulation_result(&transaction, &result, &config)?;
+    if config.replace_recent_blockhash {
+        value.replacement_blockhash = Some(latest_blockhash(simulator));
+    }
+    context_response(simulator, value)
+}
+
+fn get_account_info(simulator: &Simulator, params: &[Value]) -> Result<Value, RpcError> {
+    let pubkey = parse_pubkey(&required_param::<String>(params, 0)?)?;
+    let config: RpcAccountInfoConfig = optional_param(params, 1)?;
+    let value = simulator.rpc_account_info(&pubkey, &config)?;
+    context_response(simulator, value)
+}
+
+fn get_multiple_accounts(simulator: &Simulator, params: &[Value]) -> Result<Value, RpcError> {
+    let addresses: Vec<String> = required_param(params, 0)?;
+    let config: RpcAccountInfoConfig = optional_param(params, 1)?;
+    if addresses.len() > MAX_MULTIPLE_ACCOUNTS {
+        return Err(RpcError::invalid_params(format!(
+            "Too many inputs provided; max {MAX_MULTIPLE_ACCOUNTS}"
+        )));
+    }
+    let value = addresses
+        .iter()
+        .map(|address| Ok(simulator.rpc_account_info(&parse_pubkey(address)?, &config)?))
+        .collect::<Result<Vec<_>, RpcError>>()?;
+    context_response(simulator, value)
+}
+
+fn get_balance(simulator: &Simulator, params: &[Value]) -> Result<Value, RpcError> {
+    let pubkey = parse_pubkey(&required_param::<String>(params, 0)?)?;
+    let _config: RpcContextConfig = optional_param(params, 1)?;
+    let lamports = simulator
+        .get_account(&pubkey)
+        .map_or(0, |account| account.lamports());
+    context_response(simulator, lamports)
+}
+
+fn get_latest_blockhash(simulator: &Simulator, params: &[Value]) -> Result<Value, RpcError> {
+    let _config: RpcContextConfig = optional_param(params, 0)?;
+    context_response(simulator, latest_blockhash(simulator))
+}
+
+fn get_slot(simulator: &Simulator, params: &[Value]) -> Result<Value, RpcError> {
+    let _config: RpcContextConfig = optional_param(params, 0)?;
+    Ok(json!(simulator.clock().slot))
+}
+
+fn context_response<T: serde::Serialize>(
+    simulator: &Simulator,
+    value: T,
+) -> Result<Value, RpcError> {
+    let response = Response {
+        context: RpcResponseContext::new(simulator.clock().slot),
+        value,
+    };
+    serde_json::to_value(response).map_err(|err| RpcError {
+        code: INTERNAL_ERROR,
+        message: err.to_string(),
+    })
+}
+
+fn latest_blockhash(simulator: &Simulator) -> RpcBlockhash {
+    // The simulator doesn't track block heights, so its slot stands in for one.
+    RpcBlockhash {
+        blockhash: simulator.latest_blockhash().to_string(),
+        last_valid_block_height: simulator.clock().slot + MAX_PROCESSING_AGE as u64,
+    }
+}
+
+/// Verifies signatures, and precompiles unless the SVM verifies them during
+/// execution, in which case failures are reported in the simulation result.
+fn verify_transaction(
+    simulator: &Simulator,
+    transaction: &SanitizedTransaction,
+) -> Result<(), RpcError> {
+    if transaction.verify().is_err() {
+        return Err(RpcError {
+            code: JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE,
+            message: "Transaction signature verification failure".to_string(),
+        });
+    }
+    let feature_set = simulator.feature_set();
+    if feature_set.is_active(&move_precompile_verification_to_svm::id()) {
+        return Ok(());
+    }
+    transaction
+        .verify_precompiles(feature_set)
+        .map_err(|err| RpcError {
+            code: JSON_RPC_SERVER_ERROR_TRANSACTION_PRECOMPILE_VERIFICATION_FAILURE,
+            message: format!("Transaction precompile verification failure {err:?}"),
+        })
+}
+
+fn decode_transaction(
+    data: &str,
+    encoding: Option<UiTransactionEncoding>,
+) -> Result<VersionedTransaction, RpcError> {
+    let bytes = match encoding.unwrap_or(UiTransactionEncoding::Base58) {
+        UiTransactionEncoding::Base58 => bs58::decode(data)
+            .into_vec()
+            .map_err(|err| RpcError::invalid_params(format!("invalid base58 encoding: {err:?}")))?,
+        UiTransactionEncoding::Base64 => BASE64_STANDARD
+            .decode(data)
+            .map_err(|err| RpcError::invalid_params(format!("invalid base64 encoding: {err:?}")))?,
+        encoding => {
+            return Err(RpcError::invalid_params(format!(
+                "unsupported encoding: {encoding}. Supported encodings: base58, base64"
+            )))
+        }
+    };
+    if bytes.len() > PACKET_DATA_SIZE {
+        return Err(RpcError::invalid_params(format!(
+            "decoded transaction too large: {} bytes (max: {PACKET_DATA_SIZE} bytes)",
+            bytes.len()
+        )));
+    }
+    bincode::options()
+        .with_limit(PACKET_DATA_SIZE as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize(&bytes)
+        .map_err(|err| {
+            RpcError::invalid_params(format!("failed to deserialize VersionedTransaction: {err}"))
+        })
+}
+
+fn parse_pubkey(address: &str) -> Result<Pubkey, RpcError> {
+    address
+        .parse()
+        .map_err(|err| RpcError::invalid_params(format!("Invalid param: {err:?}")))
+}
+
+fn required_param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, RpcError> {
+    let param = params.get(index).ok_or_else(|| {
+        RpcError::invalid_params(format!(
+            "Invalid params: expected at least {} parameter(s)",
+            index + 1
+        ))
+    })?;
+    serde_json::from_value(param.clone())
+        .map_err(|err| RpcError::invalid_params(format!("Invalid params: {err}")))
+}
+
+/// Missing and `null` parameters take their default value.
+fn optional_param<T: DeserializeOwned + Default>(
+    params: &[Value],
+    index: usize,
+) -> Result<T, RpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(T::default()),
+        Some(param) => serde_json::from_value(param.clone())
+            .map_err(|err| RpcError::invalid_params(format!("Invalid params: {err}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{
+            account::AccountSharedData,
+            ed25519_program,
+            hash::Hash,
+            instruction::Instruction,
+            precompiles::PrecompileError,
+            signature::{Keypair, Signer},
+            system_instruction, system_program,
+            transaction::Transaction,
+        },
+    };
+
+    fn simulator(payer: &Pubkey) -> Simulator {
+        Simulator::try_new_from_accounts(
+            SimulatorConfig::default(),
+            vec![(
+                *payer,
+                AccountSharedData::new(10_000_000_000, 0, &system_program::id()),
+            )],
+        )
+        .unwrap()
+    }
+
+    fn encode(transaction: &Transaction) -> String {
+        BASE64_STANDARD.encode(bincode::serialize(transaction).unwrap())
+    }
+
+    #[test]
+    fn test_batch() {
+        let payer = Pubkey::new_unique();
+        let simulator = simulator(&payer);
+        let response = handle_body(
+            &simulator,
+            &json!([
+                { "jsonrpc": "2.0", "id": 1, "method": "getBalance", "params": [payer.to_string()] },
+                { "jsonrpc": "2.0", "id": 2, "method": "getSlot" },
+                { "jsonrpc": "2.0", "id": 3, "method": "getHealth" },
+            ])
+            .to_string(),
+        );
+
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["value"], 10_000_000_000u64);
+        assert_eq!(responses[1]["result"], simulator.clock().slot);
+        assert_eq!(responses[2]["id"], 3);
+        assert_eq!(responses[2]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        let simulator = simulator(&Pubkey::new_unique());
+        assert_eq!(handle_body(&simulator, "{")["error"]["code"], PARSE_ERROR);
+        assert_eq!(
+            handle_body(&simulator, "[]")["error"]["code"],
+            INVALID_REQUEST
+        );
+        let response = handle_body(
+            &simulator,
+            r#"{"jsonrpc":"2.0","id":"a","method":"getAccountInfo","params":[]}"#,
+        );
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_get_account_info() {
+        let payer = Pubkey::new_unique();
+        let simulator = simulator(&payer);
+        let response = handle_body(
+            &simulator,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "getAccountInfo",
+                "params": [payer.to_string(), { "encoding": "base64" }],
+            })
+            .to_string(),
+        );
+        let value = &response["result"]["value"];
+        assert_eq!(
+            response["result"]["context"]["slot"],
+            simulator.clock().slot
+        );
+        assert_eq!(value["lamports"], 10_000_000_000u64);
+        assert_eq!(value["owner"], system_program::id().to_string());
+        assert_eq!(value["data"], json!(["", "base64"]));
+
+        let response = handle_body(
+            &simulator,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "getAccountInfo",
+                "params": [Pubkey::new_unique().to_string()],
+            })
+            .to_string(),
+        );
+        assert_eq!(response["result"]["value"], Value::Null);
+    }
+
+    #[test]
+    fn test_simulate_transaction_with_accounts() {
+        let payer = Keypair::new();
+        let to = Pubkey::new_unique();
+        let simulator = simulator(&payer.pubkey());
+        let transaction = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &to,
+                1_000_000,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let response = handle_body(
+            &simulator,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "simulateTransaction",
+                "params": [encode(&transaction), {
+                    "encoding": "base64",
+                    "sigVerify": true,
+                    "accounts": { "addresses": [to.to_string()], "encoding": "base64" },
+                }],
+            })
+            .to_string(),
+        );
+
+        let value = &response["result"]["value"];
+        assert_eq!(value["err"], Value::Null, "{response}");
+        assert_eq!(value["accounts"][0]["lamports"], 1_000_000);
+        assert_eq!(value["unitsConsumed"], 150);
+        assert!(!value["logs"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_simulate_transaction_precompile_failure() {
+        let payer = Keypair::new();
+        let simulator = simulator(&payer.pubkey());
+        // Not a valid ed25519 instruction.
+        let transaction = Transaction::new_signed_with_payer(
+            &[Instruction::new_with_bytes(
+                ed25519_program::id(),
+                &[1, 0],
+                vec![],
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let response = handle_body(
+            &simulator,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "simulateTransaction",
+                "params": [encode(&transaction), { "encoding": "base64", "sigVerify": true }],
+            })
+            .to_string(),
+        );
+
+        // The SVM verifies precompiles, so the failure is a simulation result.
+        assert_eq!(
+            response["result"]["value"]["err"],
+            json!({ "InstructionError": [0, { "Custom": PrecompileError::InvalidInstructionDataSize as u32 }] }),
+            "{response}"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use {
+    crate::simulator::{load_addresses, MockBankCallback, Simulator, TransactionSimulationResult},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        message::{
+            v0::{LoadedAddresses, MessageAddressTableLookup},
+            AddressLoader, AddressLoaderError,
+        },
+        pubkey::Pubkey,
+        sysvar::Sysvar,
+        transaction::{SanitizedTransaction, TransactionError},
+    },
+    std::collections::HashMap,
+};
+
+/// Runs transactions one after another, committing the accounts written by each
+/// transaction so the next one observes them.
+#[derive(Clone)]
+pub struct SimulatorSession {
+    simulator: Simulator,
+    // Accounts committed by processed transactions, shadowing the simulator's
+    // accounts. Zero-lamport entries are accounts that have been closed.
+    accounts: HashMap<Pubkey, AccountSharedData>,
+}
+
+impl SimulatorSession {
+    pub fn new(simulator: Simulator) -> Self {
+        Self {
+            simulator,
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// Executes `transaction` against the session state and commits its result:
+    /// all writable accounts on success, or only the fee payer and nonce on failure.
+    pub fn process_transaction(
+        &mut self,
+        transaction: &SanitizedTransaction,
+        enable_cpi_recording: bool,
+    ) -> TransactionSimulationResult {
+        let result = self.simulator.execute_transaction(
+            self.mock_bank(),
+            transaction,
+            enable_cpi_recording,
+        );
+        self.accounts.extend(result.committed_accounts.iter().cloned());
+        result
+    }
+
+    /// Executes `transactions` in order, each one observing the state committed by
+    /// the previous ones. Execution stops at the first failing transaction, in which
+    /// case none of the bundle's state is committed.
+    pub fn process_bundle(
+        &mut self,
+        transactions: &[SanitizedTransaction],
+        enable_cpi_recording: bool,
+    ) -> BundleSimulationResult {
+        let mut working_session = self.clone();
+        let mut transaction_results = Vec::with_capacity(transactions.len());
+        for (index, transaction) in transactions.iter().enumerate() {
+            let result = working_session.process_transaction(transaction, enable_cpi_recording);
+            let failure = result.result.clone().err();
+            transaction_results.push(result);
+            if let Some(err) = failure {
+                return BundleSimulationResult {
+                    result: Err((index, err)),
+                    transaction_results,
+                };
+            }
+        }
+
+        *self = working_session;
+        BundleSimulationResult {
+            result: Ok(()),
+            transaction_results,
+        }
+    }
+
+    /// Executes `transaction` against the session state without committing it.
+    pub fn simulate_transaction(
+        &self,
+        transaction: &SanitizedTransaction,
+        enable_cpi_recording: bool,
+    ) -> TransactionSimulationResult {
+        self.simulator
+            .execute_transaction(self.mock_bank(), transaction, enable_cpi_recording)
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        match self.accounts.get(pubkey) {
+            Some(account) if account.lamports() == 0 => None,
+            Some(account) => Some(account.clone()),
+            None => self.simulator.get_account(pubkey),
+        }
+    }
+
+    /// Replaces the value of a sysvar for the rest of the session, see
+    /// [`Simulator::set_sysvar`].
+    pub fn set_sysvar<S: Sysvar>(&mut self, sysvar: &S) {
+        self.accounts.remove(&S::id());
+        self.simulator.set_sysvar(sysvar);
+    }
+
+    /// Accounts committed so far in this session.
+    pub fn committed_accounts(&self) -> &HashMap<Pubkey, AccountSharedData> {
+        &self.accounts
+    }
+
+    fn mock_bank(&self) -> MockBankCallback {
+        let mock_bank = self.simulator.mock_bank();
+        *mock_bank.account_shared_data.write().unwrap() = self.accounts.clone();
+        mock_bank
+    }
+}
+
+pub struct BundleSimulationResult {
+    /// `Ok` if every transaction succeeded, otherwise the index and error of the
+    /// transaction that failed.
+    pub result: Result<(), (usize, TransactionError)>,
+    /// Results of the executed transactions, up to and including the failing one.
+    pub transaction_results: Vec<TransactionSimulationResult>,
+}
+
+impl AddressLoader for &SimulatorSession {
+    fn load_addresses(
+        self,
+        lookups: &[MessageAddressTableLookup],
+    ) -> Result<LoadedAddresses, AddressLoaderError> {
+        load_addresses(lookups, self.simulator.clock().slot, |pubkey| {
+            self.get_account(pubkey)
+        })
+    }
+}
@@ -1,8 +1,34 @@
 use {
+    crate::{
+        account_diff::{diff_accounts, AccountDiff},
+        compute_units::{parse_compute_units, units_by_program, InstructionComputeUnits},
+        error::SimulatorError,
+        feature_set::FeatureSetConfig,
+        program::{load_upgradeable_program, ProgramDiagnostic, UpgradeableProgram},
+        rpc_response::{account_info, simulation_response},
+        session::{BundleSimulationResult, SimulatorSession},
+        token_balances::{collect_token_balances, TransactionTokenBalance},
+    },
     base64::{prelude::BASE64_STANDARD, Engine},
-    serde_json,
+    solana_account_decoder::UiAccount,
+    solana_address_lookup_table_interface::{
+        error::AddressLookupError,
+        program as address_lookup_table_program,
+        state::AddressLookupTable,
+    },
     solana_bpf_loader_program::syscalls::create_program_runtime_environment_v1,
-    solana_compute_budget::compute_budget::ComputeBudget,
+    solana_builtins::BUILTINS,
+    solana_compute_budget::{
+        compute_budget::ComputeBudget,
+        compute_budget_limits::{MAX_HEAP_FRAME_BYTES, MIN_HEAP_FRAME_BYTES},
+    },
+    solana_compute_budget_instruction::instructions_processor::process_compute_budget_instructions,
+    solana_fee::FeeFeatures,
+    solana_nonce::{
+        state::{DurableNonce, State as NonceState},
+        versions::Versions as NonceVersions,
+    },
+    solana_precompiles::get_precompiles,
     solana_program_runtime::{
         __private::{Hash, ReadableAccount},
         loaded_programs::{
@@ -10,6 +36,8 @@ use {
             ForkGraph,
             LoadProgramMetrics,
             ProgramCacheEntry,
+            ProgramCacheEntryOwner,
+            ProgramCacheEntryType,
             ProgramRuntimeEnvironments,
         },
         solana_sbpf::{
@@ -17,23 +45,40 @@ use {
             vm::Config,
         },
     },
+    solana_rpc_client_api::{
+        config::{RpcAccountInfoConfig, RpcSimulateTransactionConfig},
+        response::RpcSimulateTransactionResult,
+    },
     solana_sdk::{
-        account::{Account, AccountSharedData},
-        clock::{Clock, Slot, UnixTimestamp, MAX_PROCESSING_AGE, MAX_TRANSACTION_FORWARDING_DELAY},
-        feature_set::FeatureSet,
+        account::{create_account_shared_data_with_fields, from_account, Account, AccountSharedData},
+        account_utils::StateMut,
+        bpf_loader, bpf_loader_deprecated,
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        clock::{Clock, Epoch, Slot, UnixTimestamp, MAX_PROCESSING_AGE, MAX_TRANSACTION_FORWARDING_DELAY},
+        epoch_schedule::EpochSchedule,
+        feature_set::{self, FeatureSet},
+        fee::{FeeDetails, FeeStructure},
         inner_instruction::InnerInstructions,
-        message::{AccountKeys, AddressLoader, AddressLoaderError},
+        loader_v4::{self, LoaderV4State, LoaderV4Status},
+        message::{AddressLoader, AddressLoaderError, SanitizedMessage, VersionedMessage},
         message::v0::{LoadedAddresses, MessageAddressTableLookup},
         native_loader,
-        nonce::state::DurableNonce,
         pubkey::Pubkey,
-        sysvar::SysvarId,
-        transaction::{SanitizedTransaction, TransactionError},
+        rent::Rent,
+        rent_collector::RentCollector,
+        slot_hashes::SlotHashes,
+        sysvar::{
+            epoch_rewards::EpochRewards, last_restart_slot::LastRestartSlot, recent_blockhashes,
+            stake_history::StakeHistory, Sysvar, SysvarId,
+        },
+        transaction::{SanitizedTransaction, TransactionError, MAX_TX_ACCOUNT_LOCKS},
         transaction_context::{TransactionAccount, TransactionReturnData},
     },
     solana_svm::{
         account_loader::{CheckedTransactionDetails, TransactionCheckResult},
         account_overrides::AccountOverrides,
+        nonce_info::NonceInfo,
+        rollback_accounts::RollbackAccounts,
         transaction_error_metrics::TransactionErrorMetrics,
         transaction_processing_callback::TransactionProcessingCallback,
         transaction_processing_result::{
@@ -49,21 +94,21 @@ use {
             TransactionProcessingEnvironment,
         },
     },
-    solana_system_program::system_processor,
+    solana_svm_transaction::svm_message::SVMMessage,
     std::{
         collections::HashMap,
         fs,
         path::PathBuf,
         str::FromStr,
-        sync::{Arc, RwLock},
+        sync::{Arc, RwLock, RwLockWriteGuard},
         time::{SystemTime, UNIX_EPOCH},
     },
 };
 
+// Default slot of `ClockConfig` when the accounts have no `Clock` sysvar.
 const EXECUTION_SLOT: u64 = 5; // The execution slot must be greater than the deployment slot
-const EXECUTION_EPOCH: u64 = 2; // The execution epoch must be greater than the deployment epoch
+// Slot all programs are compiled at, so they are visible at any execution slot.
 const DEPLOYMENT_SLOT: u64 = 0;
-const DEPLOYMENT_EPOCH: u64 = 0;
 
 pub struct MockForkGraph {}
 
@@ -80,52 +125,300 @@ impl ForkGraph for MockForkGraph {
 #[derive(Debug, Default, Clone)]
 pub struct SimulatorConfig {
     pub accounts_path: PathBuf,
+    pub feature_set: FeatureSetConfig,
+    pub clock: ClockConfig,
+    /// Most recent blockhash, also used to advance durable nonces.
+    pub blockhash: Hash,
+    /// Blockhashes preceding `blockhash` in the recent blockhash queue, newest
+    /// first. Only used when `check_transaction_age` is set.
+    pub recent_blockhashes: Vec<Hash>,
+    /// Rejects transactions with `BlockhashNotFound` unless their blockhash is
+    /// in the recent blockhash queue or they use a valid durable nonce, whose
+    /// account is then advanced. Otherwise any blockhash is accepted.
+    pub check_transaction_age: bool,
+    /// Entries of the `SlotHashes` sysvar, newest first. Defaults to the
+    /// `SlotHashes` account in the loaded accounts, if any.
+    pub slot_hashes: Option<Vec<(Slot, Hash)>>,
+    /// Fee charged per transaction and precompile signature. Defaults to 5000
+    /// lamports, as on mainnet; zero disables fees, prioritization fees included.
+    pub lamports_per_signature: Option<u64>,
+    /// Refuses to read the wall clock, so that simulations of the same
+    /// transactions against the same accounts produce identical results.
+    /// Requires `clock.unix_timestamp` to be set.
+    pub deterministic: bool,
+    /// Instruction costs and runtime limits. Its `compute_unit_limit` and
+    /// `heap_size` are ignored in favour of the ones transactions request with
+    /// ComputeBudget instructions. Defaults to `ComputeBudget::default()`.
+    pub compute_budget: Option<ComputeBudget>,
+    /// Replaces the compute unit limit requested by transactions.
+    pub compute_unit_limit: Option<u64>,
+    /// Replaces the heap size requested by transactions. Must be a multiple of
+    /// 1024 between 32 KiB and 256 KiB.
+    pub heap_size: Option<u32>,
+    /// Maximum number of accounts a transaction may reference. Defaults to 128
+    /// when `increase_tx_account_lock_limit` is active and 64 otherwise.
+    pub transaction_account_lock_limit: Option<usize>,
+}
+
+/// Values of the `Clock` sysvar. Transactions are executed at `slot` and `epoch`.
+#[derive(Debug, Default, Clone)]
+pub struct ClockConfig {
+    /// Defaults to the slot of the `Clock` sysvar in the loaded accounts, if any.
+    /// Must follow the last extension of every loaded lookup table.
+    pub slot: Option<Slot>,
+    /// Defaults to the epoch of `slot` under the `EpochSchedule` sysvar.
+    pub epoch: Option<Epoch>,
+    /// Defaults to the wall clock when the simulator is created.
+    pub unix_timestamp: Option<UnixTimestamp>,
+    /// Defaults to ten seconds before `unix_timestamp`.
+    pub epoch_start_timestamp: Option<UnixTimestamp>,
+}
+
+impl ClockConfig {
+    fn to_clock(&self, default_slot: Slot, epoch_schedule: &EpochSchedule) -> Clock {
+        let slot = self.slot.unwrap_or(default_slot);
+        let unix_timestamp = self.unix_timestamp.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards")
+                .as_secs() as UnixTimestamp
+        });
+        let epoch = self
+            .epoch
+            .unwrap_or_else(|| epoch_schedule.get_epoch(slot));
+        Clock {
+            slot,
+            epoch_start_timestamp: self
+                .epoch_start_timestamp
+                .unwrap_or(unix_timestamp.saturating_sub(10)),
+            epoch,
+            leader_schedule_epoch: epoch_schedule.get_leader_schedule_epoch(slot),
+            unix_timestamp,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Simulator {
-    account_map: Vec<(Pubkey, AccountSharedData)>,
+    // Immutable account state shared by every simulation; writes go to a
+    // per-simulation overlay in `MockBankCallback`.
+    accounts: Arc<HashMap<Pubkey, AccountSharedData>>,
+    feature_set: Arc<FeatureSet>,
+    clock: Clock,
+    blockhash: Hash,
+    // Blockhashes preceding `blockhash`, newest first.
+    recent_blockhashes: Arc<Vec<Hash>>,
+    check_transaction_age: bool,
+    lamports_per_signature: u64,
+    compute_budget: Option<ComputeBudget>,
+    compute_unit_limit: Option<u64>,
+    heap_size: Option<u32>,
+    transaction_account_lock_limit: usize,
+    // Built from the `Rent` and `EpochSchedule` sysvars.
+    rent_collector: RentCollector,
     transaction_processor: Arc<RwLock<TransactionBatchProcessor<MockForkGraph>>>,
+    // The program cache only keeps a weak reference to the fork graph.
+    _fork_graph: Arc<RwLock<MockForkGraph>>,
+    // Account each cached program was compiled from, used to detect programs
+    // that changed since they were loaded. The program cache is shared by every
+    // clone and session, so simulations hold this lock until they have executed:
+    // shared while the cache matches their programs, exclusive while updating it.
+    loaded_programs: Arc<RwLock<HashMap<Pubkey, LoadedProgram>>>,
 }
 
 impl Simulator {
     pub fn new(config: SimulatorConfig) -> Self {
+        Self::try_new(config).expect("failed to create simulator")
+    }
+
+    pub fn try_new(config: SimulatorConfig) -> Result<Self, SimulatorError> {
         let accounts_json_path = config.accounts_path.clone();
-        let accounts_data: String = fs::read_to_string(accounts_json_path).unwrap();
-        let accounts_data: serde_json::Value = serde_json::from_str(&accounts_data).unwrap();
+        let accounts_data: String =
+            fs::read_to_string(&accounts_json_path).map_err(|source| SimulatorError::ReadAccounts {
+                path: accounts_json_path,
+                source,
+            })?;
+        let accounts_data: serde_json::Value = serde_json::from_str(&accounts_data)
+            .map_err(|err| SimulatorError::ParseAccounts(err.to_string()))?;
         let accounts_slice: Vec<(Pubkey, AccountSharedData)> = accounts_data["accounts"]
             .as_array()
-            .unwrap()
+            .ok_or_else(|| SimulatorError::ParseAccounts("missing `accounts` array".to_string()))?
             .iter()
-            .map(|acc| {
-                let pubkey = Pubkey::from_str(acc["pubkey"].as_str().unwrap()).unwrap();
-                let account = acc["account"].as_object().unwrap();
-                let owner = account["owner"].as_str().unwrap();
-                let data = account["data"].as_array().unwrap()[0].as_str().unwrap();
-                let acc_data = AccountSharedData::from(Account {
-                    lamports: account["lamports"].as_u64().unwrap(),
-                    data: BASE64_STANDARD.decode(data).unwrap(),
-                    owner: Pubkey::from_str(owner).unwrap(),
-                    executable: account["executable"].as_bool().unwrap(),
-                    rent_epoch: account["rentEpoch"].as_u64().unwrap(),
-                });
-                (pubkey, acc_data)
-            })
-            .collect();
+            .enumerate()
+            .map(|(index, acc)| parse_account(index, acc))
+            .collect::<Result<_, _>>()?;
 
-        Self::new_with_accounts(accounts_slice)
+        Self::try_new_from_accounts(config, accounts_slice)
     }
 
     pub fn new_with_accounts(accounts: Vec<(Pubkey, AccountSharedData)>) -> Self {
+        Self::try_new_with_accounts(accounts).expect("failed to create simulator")
+    }
+
+    pub fn try_new_with_accounts(
+        accounts: Vec<(Pubkey, AccountSharedData)>,
+    ) -> Result<Self, SimulatorError> {
+        Self::try_new_from_accounts(SimulatorConfig::default(), accounts)
+    }
+
+    /// Creates a simulator over `accounts`, registering the builtins and compiling
+    /// every program once so simulations can reuse them. `config.accounts_path` is
+    /// ignored.
+    pub fn try_new_from_accounts(
+        config: SimulatorConfig,
+        accounts: Vec<(Pubkey, AccountSharedData)>,
+    ) -> Result<Self, SimulatorError> {
+        if config.deterministic && config.clock.unix_timestamp.is_none() {
+            return Err(SimulatorError::InvalidConfig(
+                "deterministic mode requires `clock.unix_timestamp`".to_string(),
+            ));
+        }
+        if let Some(heap_size) = config.heap_size {
+            if !(MIN_HEAP_FRAME_BYTES..=MAX_HEAP_FRAME_BYTES).contains(&heap_size)
+                || heap_size % 1024 != 0
+            {
+                return Err(SimulatorError::InvalidConfig(format!(
+                    "invalid heap size {heap_size}"
+                )));
+            }
+        }
+        let fork_graph = Arc::new(RwLock::new(MockForkGraph {}));
+        let mut loaded_programs = HashMap::new();
+
+        let accounts = Arc::new(HashMap::from_iter(accounts));
+        let feature_set = Arc::new(config.feature_set.build(&accounts));
+        let mock_bank = MockBankCallback::new(accounts.clone(), feature_set.clone());
+        if let Some(slot_hashes) = &config.slot_hashes {
+            mock_bank.account_shared_data.write().unwrap().insert(
+                SlotHashes::id(),
+                create_account_shared_data_with_fields(&SlotHashes::new(slot_hashes), (1, 0)),
+            );
+        }
+        let lamports_per_signature = config
+            .lamports_per_signature
+            .unwrap_or(FeeStructure::default().lamports_per_signature);
+        // Durable nonce instructions require a non-empty `RecentBlockhashes` sysvar.
+        mock_bank.account_shared_data.write().unwrap().insert(
+            recent_blockhashes::id(),
+            recent_blockhashes_account(
+                std::iter::once(&config.blockhash).chain(&config.recent_blockhashes),
+                lamports_per_signature,
+            ),
+        );
+        // A bank always has these sysvars. Default the ones missing from `accounts`
+        // to their genesis values, so they can be read and overridden.
+        insert_missing_sysvar(&mock_bank, &Rent::default());
+        insert_missing_sysvar(&mock_bank, &EpochSchedule::default());
+        insert_missing_sysvar(&mock_bank, &SlotHashes::default());
+        insert_missing_sysvar(&mock_bank, &StakeHistory::default());
+        insert_missing_sysvar(&mock_bank, &EpochRewards::default());
+        insert_missing_sysvar(&mock_bank, &LastRestartSlot::default());
+        let epoch_schedule = mock_bank
+            .get_account_shared_data(&EpochSchedule::id())
+            .and_then(|account| from_account::<EpochSchedule, _>(&account))
+            .unwrap_or_default();
+        let accounts_slot = mock_bank
+            .get_account_shared_data(&Clock::id())
+            .and_then(|account| from_account::<Clock, _>(&account))
+            .map_or(EXECUTION_SLOT, |clock| clock.slot);
+        let clock = config.clock.to_clock(accounts_slot, &epoch_schedule);
+        check_lookup_tables(&accounts, clock.slot)?;
         let batch_processor = TransactionBatchProcessor::<MockForkGraph>::new_uninitialized(
-            EXECUTION_SLOT,
-            EXECUTION_EPOCH,
+            clock.slot,
+            clock.epoch,
+        );
+        create_executable_environment(
+            fork_graph.clone(),
+            &mock_bank,
+            &batch_processor,
+            &clock,
+            &config.compute_budget.unwrap_or_default(),
+        )?;
+        load_programs(
+            &program_loads(accounts.keys(), &mock_bank),
+            &batch_processor,
+            &mut loaded_programs,
         );
 
-        Self {
-            account_map: accounts,
+        let transaction_account_lock_limit = config.transaction_account_lock_limit.unwrap_or(
+            if feature_set.is_active(&feature_set::increase_tx_account_lock_limit::id()) {
+                MAX_TX_ACCOUNT_LOCKS
+            } else {
+                64
+            },
+        );
+
+        let accounts = mock_bank.into_accounts();
+        Ok(Self {
+            rent_collector: rent_collector_from_accounts(&accounts, clock.epoch),
+            accounts: Arc::new(accounts),
+            feature_set,
+            clock,
+            blockhash: config.blockhash,
+            recent_blockhashes: Arc::new(config.recent_blockhashes),
+            check_transaction_age: config.check_transaction_age,
+            lamports_per_signature,
+            compute_budget: config.compute_budget,
+            compute_unit_limit: config.compute_unit_limit,
+            heap_size: config.heap_size,
+            transaction_account_lock_limit,
             transaction_processor: Arc::new(RwLock::new(batch_processor)),
+            _fork_graph: fork_graph,
+            loaded_programs: Arc::new(RwLock::new(loaded_programs)),
+        })
+    }
+
+    /// Simulates `transaction` after verifying it with [`Self::verify_transaction`],
+    /// like the RPC's `sigVerify`.
+    pub fn simulate_transaction(
+        &self,
+        transaction: &SanitizedTransaction,
+        enable_cpi_recording: bool,
+    ) -> TransactionSimulationResult {
+        if let Err(err) = self.verify_transaction(transaction) {
+            return TransactionSimulationResult::new_error(err);
         }
+        self.simulate_transaction_unchecked(transaction, enable_cpi_recording)
+    }
+
+    /// Verifies the signatures and precompile instructions of `transaction`. Bad
+    /// signatures fail with `SignatureFailure`; a precompile instruction that fails
+    /// verification fails with `InstructionError(index, Custom(code))`, where `code`
+    /// is the `PrecompileError`.
+    pub fn verify_transaction(
+        &self,
+        transaction: &SanitizedTransaction,
+    ) -> solana_sdk::transaction::Result<()> {
+        transaction.verify()?;
+        transaction.verify_precompiles(&self.feature_set)
+    }
+
+    /// Returns `transaction` with its blockhash replaced by the simulator's most
+    /// recent blockhash, like the RPC's `replaceRecentBlockhash`. The signatures are
+    /// kept and no longer match, so simulate the result with
+    /// [`Self::simulate_transaction_unchecked`].
+    pub fn replace_recent_blockhash(
+        &self,
+        transaction: &SanitizedTransaction,
+    ) -> solana_sdk::transaction::Result<SanitizedTransaction> {
+        let (message, message_hash) = match transaction.message().clone() {
+            SanitizedMessage::Legacy(mut legacy) => {
+                legacy.message.to_mut().recent_blockhash = self.blockhash;
+                let message_hash = legacy.message.hash();
+                (SanitizedMessage::Legacy(legacy), message_hash)
+            }
+            SanitizedMessage::V0(mut loaded) => {
+                loaded.message.to_mut().recent_blockhash = self.blockhash;
+                let message_hash = VersionedMessage::V0(loaded.message.clone().into_owned()).hash();
+                (SanitizedMessage::V0(loaded), message_hash)
+            }
+        };
+        SanitizedTransaction::try_new_from_fields(
+            message,
+            message_hash,
+            transaction.is_simple_vote_transaction(),
+            transaction.signatures().to_vec(),
+        )
     }
 
     pub fn simulate_transaction_unchecked(
@@ -133,58 +426,82 @@ impl Simulator {
         transaction: &SanitizedTransaction,
         enable_cpi_recording: bool,
     ) -> TransactionSimulationResult {
-        let mut mock_bank = MockBankCallback::new(self.account_map.clone());
-        let transaction_processor = self.transaction_processor.read().unwrap();
+        self.execute_transaction(self.mock_bank(), transaction, enable_cpi_recording)
+    }
 
-        let account_keys = transaction.message().account_keys();
-        let number_of_accounts = account_keys.len();
-        let account_overrides = AccountOverrides::default();
+    /// Like [`Self::simulate_transaction_unchecked`], but `account_overrides` shadow
+    /// the simulator's accounts for this simulation only. An override with zero
+    /// lamports hides the account. Sysvars are read from the sysvar cache, so
+    /// override them with [`Self::set_sysvar`] instead.
+    pub fn simulate_transaction_unchecked_with_overrides(
+        &self,
+        transaction: &SanitizedTransaction,
+        account_overrides: &HashMap<Pubkey, AccountSharedData>,
+        enable_cpi_recording: bool,
+    ) -> TransactionSimulationResult {
+        let mock_bank = self.mock_bank();
+        mock_bank.account_shared_data.write().unwrap().extend(
+            account_overrides
+                .iter()
+                .map(|(pubkey, account)| (*pubkey, account.clone())),
+        );
+        self.execute_transaction(mock_bank, transaction, enable_cpi_recording)
+    }
 
-        let fork_graph = Arc::new(RwLock::new(MockForkGraph {}));
+    /// Simulates `transactions` as an atomic bundle against the simulator's accounts.
+    pub fn simulate_bundle(
+        &self,
+        transactions: &[SanitizedTransaction],
+        enable_cpi_recording: bool,
+    ) -> BundleSimulationResult {
+        self.session().process_bundle(transactions, enable_cpi_recording)
+    }
 
-        create_executable_environment(
-            fork_graph.clone(),
-            &account_keys,
-            &mut mock_bank,
-            &transaction_processor,
-        );
+    /// Starts a session whose transactions build on each other's committed state.
+    pub fn session(&self) -> SimulatorSession {
+        SimulatorSession::new(self.clone())
+    }
 
-        // Add the system program builtin.
-        transaction_processor.add_builtin(
-            &mock_bank,
-            solana_system_program::id(),
-            "system_program",
-            ProgramCacheEntry::new_builtin(
-                0,
-                b"system_program".len(),
-                system_processor::Entrypoint::vm,
-            ),
-        );
-        // Add the BPF Loader v2 builtin, for the SPL Token program.
-        transaction_processor.add_builtin(
-            &mock_bank,
-            solana_sdk::bpf_loader_upgradeable::id(),
-            "solana_bpf_loader_upgradeable_program",
-            ProgramCacheEntry::new_builtin(
-                0,
-                b"solana_bpf_loader_upgradeable_program".len(),
-                solana_bpf_loader_program::Entrypoint::vm,
-            ),
-        );
+    /// Executes `transaction` against `mock_bank`.
+    pub(crate) fn execute_transaction(
+        &self,
+        mock_bank: MockBankCallback,
+        transaction: &SanitizedTransaction,
+        enable_cpi_recording: bool,
+    ) -> TransactionSimulationResult {
+        let transaction_processor = self.transaction_processor.read().unwrap();
 
-        // Add the BPF Loader builtin, for the SPL Token program.
-        transaction_processor.add_builtin(
-            &mock_bank,
-            solana_sdk::bpf_loader::id(),
-            "solana_bpf_loader_program",
-            ProgramCacheEntry::new_builtin(
-                0,
-                b"solana_bpf_loader_program".len(),
-                solana_bpf_loader_program::Entrypoint::vm,
-            ),
+        let account_keys = transaction.message().account_keys();
+        let number_of_accounts = account_keys.len();
+        // Overrides are applied to `mock_bank`'s overlay instead, since the SVM's
+        // `AccountOverrides` can only hold the `SlotHistory` sysvar.
+        let account_overrides = AccountOverrides::default();
+        let pre_simulation_accounts = account_keys
+            .iter()
+            .map(|key| {
+                let account = mock_bank.get_account_shared_data(key).unwrap_or_default();
+                (*key, account)
+            })
+            .collect::<Vec<_>>();
+        let pre_token_balances = collect_token_balances(
+            transaction.message(),
+            self.clock.unix_timestamp,
+            |pubkey| mock_bank.get_account_shared_data(pubkey),
         );
 
-        transaction_processor.fill_missing_sysvar_cache_entries(&mock_bank);
+        let program_loads = program_loads(account_keys.iter(), &mock_bank);
+        let loaded_programs = {
+            let loaded_programs = self.loaded_programs.read().unwrap();
+            if is_loaded(&program_loads, &loaded_programs) {
+                loaded_programs
+            } else {
+                drop(loaded_programs);
+                let mut loaded_programs = self.loaded_programs.write().unwrap();
+                load_programs(&program_loads, &transaction_processor, &mut loaded_programs);
+                RwLockWriteGuard::downgrade(loaded_programs)
+            }
+        };
+        let program_diagnostics = program_diagnostics(&program_loads, &loaded_programs);
 
         let batch = self.prepare_unlocked_batch_from_single_tx(transaction);
         let LoadAndExecuteTransactionsOutput {
@@ -200,7 +517,7 @@ impl Simulator {
             TransactionProcessingConfig {
                 account_overrides: Some(&account_overrides),
                 check_program_modification_slot: false,
-                compute_budget: Some(ComputeBudget::default()),
+                compute_budget: self.compute_budget(transaction),
                 log_messages_bytes_limit: None,
                 limit_to_load_programs: true,
                 recording_config: ExecutionRecordingConfig {
@@ -208,15 +525,67 @@ impl Simulator {
                     enable_log_recording: true,
                     enable_return_data_recording: true,
                 },
-                transaction_account_lock_limit: Some(64),
+                transaction_account_lock_limit: Some(self.transaction_account_lock_limit),
             },
         );
 
+        drop(loaded_programs);
+
         let processing_result = processing_results
             .pop()
             .unwrap_or(Err(TransactionError::InvalidProgramForExecution));
 
         let flattened_result = processing_result.flattened_result();
+        let (units_consumed, loaded_accounts_data_size, fee) = match &processing_result {
+            Ok(processed_tx) => (
+                processed_tx.executed_units(),
+                processed_tx.loaded_accounts_data_size(),
+                processed_tx.fee_details().total_fee(),
+            ),
+            Err(_) => (0, 0, 0),
+        };
+        let fee_payer = transaction.message().fee_payer();
+        let committed_accounts = match &processing_result {
+            Ok(ProcessedTransaction::Executed(executed_tx))
+                if executed_tx.execution_details.status.is_ok() =>
+            {
+                executed_tx
+                    .loaded_transaction
+                    .accounts
+                    .iter()
+                    .take(number_of_accounts)
+                    .enumerate()
+                    .filter(|(index, _)| transaction.message().is_writable(*index))
+                    .map(|(_, account)| account.clone())
+                    .collect()
+            }
+            Ok(ProcessedTransaction::Executed(executed_tx)) => {
+                rollback_accounts(fee_payer, &executed_tx.loaded_transaction.rollback_accounts)
+            }
+            Ok(ProcessedTransaction::FeesOnly(fees_only_tx)) => {
+                rollback_accounts(fee_payer, &fees_only_tx.rollback_accounts)
+            }
+            Err(_) => vec![],
+        };
+        // The state a bank would hold after committing the transaction.
+        let committed = committed_accounts.iter().cloned().collect::<HashMap<_, _>>();
+        let get_committed_account = |pubkey: &Pubkey| match committed.get(pubkey) {
+            Some(account) => (account.lamports() != 0).then(|| account.clone()),
+            None => mock_bank.get_account_shared_data(pubkey),
+        };
+        let pre_balances = pre_simulation_accounts
+            .iter()
+            .map(|(_, account)| account.lamports())
+            .collect();
+        let post_balances = account_keys
+            .iter()
+            .map(|pubkey| get_committed_account(pubkey).map_or(0, |account| account.lamports()))
+            .collect();
+        let post_token_balances = collect_token_balances(
+            transaction.message(),
+            self.clock.unix_timestamp,
+            get_committed_account,
+        );
         let (post_simulation_accounts, logs, return_data, inner_instructions) =
             match processing_result {
                 Ok(processed_tx) => match processed_tx {
@@ -240,25 +609,135 @@ impl Simulator {
                 Err(_) => (vec![], None, None, None),
             };
         let logs = logs.unwrap_or_default();
-        let units_consumed: u64 = 0;
+        let compute_units = parse_compute_units(&logs, units_consumed);
 
         TransactionSimulationResult {
             result: flattened_result,
             logs,
+            pre_simulation_accounts,
             post_simulation_accounts,
             units_consumed,
+            compute_units,
+            loaded_accounts_data_size,
+            fee,
             return_data,
             inner_instructions,
+            pre_balances,
+            post_balances,
+            pre_token_balances,
+            post_token_balances,
+            program_diagnostics,
+            committed_accounts,
         }
     }
 
+    pub(crate) fn mock_bank(&self) -> MockBankCallback {
+        MockBankCallback::new(self.accounts.clone(), self.feature_set.clone())
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.accounts.get(pubkey).cloned()
+    }
+
+    /// Deployment details of the upgradeable program `program_id`.
+    pub fn get_upgradeable_program(
+        &self,
+        program_id: &Pubkey,
+    ) -> Result<UpgradeableProgram, ProgramDiagnostic> {
+        let program_account = self.get_account(program_id).ok_or_else(|| ProgramDiagnostic {
+            program_id: *program_id,
+            reason: "program account not found".to_string(),
+        })?;
+        load_upgradeable_program(program_id, &program_account, |address| {
+            self.get_account(address)
+        })
+        .map(|(program, _)| program)
+    }
+
+    /// The `simulateTransaction` RPC response for `result`, the simulation of
+    /// `transaction`. `config` selects the returned accounts and whether inner
+    /// instructions are included; `replacementBlockhash` is left to the caller.
+    pub fn rpc_simulation_result(
+        &self,
+        transaction: &SanitizedTransaction,
+        result: &TransactionSimulationResult,
+        config: &RpcSimulateTransactionConfig,
+    ) -> Result<RpcSimulateTransactionResult, SimulatorError> {
+        simulation_response(
+            result,
+            &transaction.message().account_keys(),
+            config,
+            self.clock.unix_timestamp,
+            |pubkey| self.get_account(pubkey),
+        )
+    }
+
+    /// The account `pubkey` as returned by the `getAccountInfo` RPC, encoded as
+    /// `config` requests. `None` if the account doesn't exist.
+    pub fn rpc_account_info(
+        &self,
+        pubkey: &Pubkey,
+        config: &RpcAccountInfoConfig,
+    ) -> Result<Option<UiAccount>, SimulatorError> {
+        account_info(pubkey, config, self.clock.unix_timestamp, |pubkey| {
+            self.get_account(pubkey)
+        })
+    }
+
+    /// Most recent blockhash, also used to advance durable nonces.
+    pub fn latest_blockhash(&self) -> Hash {
+        self.blockhash
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn feature_set(&self) -> &FeatureSet {
+        &self.feature_set
+    }
+
+    pub fn get_sysvar<S: Sysvar>(&self) -> Option<S> {
+        self.accounts
+            .get(&S::id())
+            .and_then(|account| from_account(account))
+    }
+
+    /// Replaces the value of a sysvar, updating both its account and the sysvar
+    /// cache programs read from. Setting `Clock` also moves execution to its slot
+    /// and epoch.
+    pub fn set_sysvar<S: Sysvar>(&mut self, sysvar: &S) {
+        let lamports = self
+            .accounts
+            .get(&S::id())
+            .map_or(1, |account| account.lamports().max(1));
+        let accounts = Arc::make_mut(&mut self.accounts);
+        accounts.insert(
+            S::id(),
+            create_account_shared_data_with_fields(sysvar, (lamports, 0)),
+        );
+        if S::id() == Clock::id() {
+            self.clock = from_account(&accounts[&Clock::id()]).unwrap();
+        }
+        self.rent_collector = rent_collector_from_accounts(accounts, self.clock.epoch);
+
+        // Clones of this simulator share the transaction processor, so give this one
+        // its own processor, and sysvar cache, over the same program cache.
+        let transaction_processor = self
+            .transaction_processor
+            .read()
+            .unwrap()
+            .new_from(self.clock.slot, self.clock.epoch);
+        transaction_processor.fill_missing_sysvar_cache_entries(&self.mock_bank());
+        self.transaction_processor = Arc::new(RwLock::new(transaction_processor));
+    }
+
     fn prepare_unlocked_batch_from_single_tx<'a>(
         &'a self,
         transaction: &'a SanitizedTransaction,
     ) -> TransactionBatch<'a> {
-        let tx_account_lock_limit = solana_sdk::transaction::MAX_TX_ACCOUNT_LOCKS;
         let lock_result = transaction
-            .get_account_locks(tx_account_lock_limit)
+            .get_account_locks(self.transaction_account_lock_limit)
             .map(|_| ());
         let batch = TransactionBatch::new(
             vec![lock_result],
@@ -267,27 +746,115 @@ impl Simulator {
         batch
     }
 
+    // `None` lets the SVM derive the budget from the transaction's ComputeBudget
+    // instructions, as a validator does. Otherwise the same derivation is done here
+    // so the configured costs and overrides can be applied on top.
+    fn compute_budget(&self, transaction: &SanitizedTransaction) -> Option<ComputeBudget> {
+        if self.compute_budget.is_none()
+            && self.compute_unit_limit.is_none()
+            && self.heap_size.is_none()
+        {
+            return None;
+        }
+        // Invalid ComputeBudget instructions are reported by the SVM.
+        let limits = process_compute_budget_instructions(
+            transaction.program_instructions_iter(),
+            &self.feature_set,
+        )
+        .ok()?;
+        Some(ComputeBudget {
+            compute_unit_limit: self
+                .compute_unit_limit
+                .unwrap_or(u64::from(limits.compute_unit_limit)),
+            heap_size: self.heap_size.unwrap_or(limits.updated_heap_bytes),
+            ..self.compute_budget.unwrap_or_default()
+        })
+    }
+
     fn check_transaction_age(
         &self,
-        _tx: &SanitizedTransaction,
-        _max_age: usize,
-        _next_durable_nonce: &DurableNonce,
-        _error_counters: &mut TransactionErrorMetrics,
+        bank: &MockBankCallback,
+        tx: &SanitizedTransaction,
+        max_age: usize,
+        next_durable_nonce: &DurableNonce,
+        error_counters: &mut TransactionErrorMetrics,
     ) -> TransactionCheckResult {
-        Ok(CheckedTransactionDetails::new(
-            None,
-            u64::default(),
+        let recent_blockhash = tx.message().recent_blockhash();
+        if !self.check_transaction_age || self.is_hash_valid_for_age(recent_blockhash, max_age) {
+            return Ok(CheckedTransactionDetails::new(
+                None,
+                self.lamports_per_signature,
+            ));
+        }
+        if let Some((nonce, previous_lamports_per_signature)) =
+            self.check_load_and_advance_message_nonce_account(bank, tx, next_durable_nonce)
+        {
+            return Ok(CheckedTransactionDetails::new(
+                Some(nonce),
+                previous_lamports_per_signature,
+            ));
+        }
+        error_counters.blockhash_not_found += 1;
+        Err(TransactionError::BlockhashNotFound)
+    }
+
+    // The latest blockhash has age zero, the blockhashes before it count up.
+    fn is_hash_valid_for_age(&self, hash: &Hash, max_age: usize) -> bool {
+        std::iter::once(&self.blockhash)
+            .chain(self.recent_blockhashes.iter())
+            .take(max_age.saturating_add(1))
+            .any(|recent| recent == hash)
+    }
+
+    // Returns the nonce account advanced to `next_durable_nonce`, along with the
+    // fee rate stored in it, if `tx` is a durable nonce transaction that may run.
+    fn check_load_and_advance_message_nonce_account(
+        &self,
+        bank: &MockBankCallback,
+        tx: &SanitizedTransaction,
+        next_durable_nonce: &DurableNonce,
+    ) -> Option<(NonceInfo, u64)> {
+        let message = tx.message();
+        if message.recent_blockhash() == next_durable_nonce.as_hash() {
+            // The nonce was already advanced to the latest blockhash.
+            return None;
+        }
+        let nonce_address = message.get_durable_nonce()?;
+        let mut nonce_account = bank.get_account_shared_data(nonce_address)?;
+        let nonce_data =
+            solana_nonce_account::verify_nonce_account(&nonce_account, message.recent_blockhash())?;
+        // The advance instruction is always the first one.
+        if !message
+            .get_ix_signers(0)
+            .any(|signer| signer == &nonce_data.authority)
+        {
+            return None;
+        }
+
+        let previous_lamports_per_signature = nonce_data.get_lamports_per_signature();
+        let next_nonce_state = NonceState::new_initialized(
+            &nonce_data.authority,
+            *next_durable_nonce,
+            self.lamports_per_signature,
+        );
+        nonce_account
+            .set_state(&NonceVersions::new(next_nonce_state))
+            .ok()?;
+        Some((
+            NonceInfo::new(*nonce_address, nonce_account),
+            previous_lamports_per_signature,
         ))
     }
 
     fn check_age(
         &self,
+        bank: &MockBankCallback,
         sanitized_txs: &[impl core::borrow::Borrow<SanitizedTransaction>],
         lock_results: &[solana_sdk::transaction::Result<()>],
         max_age: usize,
         error_counters: &mut TransactionErrorMetrics,
     ) -> Vec<TransactionCheckResult> {
-        let last_blockhash = Hash::default();
+        let last_blockhash = self.blockhash;
         let next_durable_nonce = DurableNonce::from_blockhash(&last_blockhash);
 
         sanitized_txs
@@ -295,6 +862,7 @@ impl Simulator {
             .zip(lock_results)
             .map(|(tx, lock_res)| match lock_res {
                 Ok(()) => self.check_transaction_age(
+                    bank,
                     tx.borrow(),
                     max_age,
                     &next_durable_nonce,
@@ -306,8 +874,8 @@ impl Simulator {
     }
 
     fn last_blockhash_and_lamports_per_signature(&self) -> (Hash, u64) {
-        let last_hash = Hash::default();
-        let last_lamports_per_signature = u64::default();
+        let last_hash = self.blockhash;
+        let last_lamports_per_signature = self.lamports_per_signature;
         (last_hash, last_lamports_per_signature)
     }
 
@@ -322,6 +890,7 @@ impl Simulator {
         let mut error_counters = TransactionErrorMetrics::default();
 
         let check_results = self.check_age(
+            bank,
             sanitized_txs,
             batch.lock_results(),
             max_age,
@@ -335,7 +904,7 @@ impl Simulator {
             epoch_total_stake: 0,
             feature_set: Arc::clone(&bank.feature_set),
             fee_lamports_per_signature: lamports_per_signature,
-            rent_collector: None,
+            rent_collector: Some(&self.rent_collector),
         };
 
         let sanitized_output = self
@@ -356,25 +925,264 @@ impl Simulator {
     }
 }
 
+/// Accounts that are still committed when a transaction fails: the fee payer
+/// after the fee is charged and the advanced nonce account, if any.
+fn rollback_accounts(
+    fee_payer: &Pubkey,
+    rollback_accounts: &RollbackAccounts,
+) -> Vec<TransactionAccount> {
+    match rollback_accounts {
+        RollbackAccounts::FeePayerOnly { fee_payer_account } => {
+            vec![(*fee_payer, fee_payer_account.clone())]
+        }
+        RollbackAccounts::SameNonceAndFeePayer { nonce } => {
+            vec![(*nonce.address(), nonce.account().clone())]
+        }
+        RollbackAccounts::SeparateNonceAndFeePayer {
+            nonce,
+            fee_payer_account,
+        } => vec![
+            (*nonce.address(), nonce.account().clone()),
+            (*fee_payer, fee_payer_account.clone()),
+        ],
+    }
+}
+
+#[allow(deprecated)]
+fn recent_blockhashes_account<'a>(
+    blockhashes: impl Iterator<Item = &'a Hash>,
+    lamports_per_signature: u64,
+) -> AccountSharedData {
+    let recent_blockhashes = blockhashes
+        .take(recent_blockhashes::MAX_ENTRIES)
+        .enumerate()
+        .map(|(age, blockhash)| {
+            recent_blockhashes::IterItem(age as u64, blockhash, lamports_per_signature)
+        })
+        .collect::<recent_blockhashes::RecentBlockhashes>();
+    create_account_shared_data_with_fields(&recent_blockhashes, (1, 0))
+}
+
+fn rent_collector_from_accounts(
+    accounts: &HashMap<Pubkey, AccountSharedData>,
+    epoch: Epoch,
+) -> RentCollector {
+    let default_rent_collector = RentCollector::default();
+    RentCollector {
+        epoch,
+        epoch_schedule: accounts
+            .get(&EpochSchedule::id())
+            .and_then(from_account)
+            .unwrap_or(default_rent_collector.epoch_schedule),
+        rent: accounts
+            .get(&Rent::id())
+            .and_then(from_account)
+            .unwrap_or(default_rent_collector.rent),
+        ..default_rent_collector
+    }
+}
+
+fn parse_account(
+    index: usize,
+    acc: &serde_json::Value,
+) -> Result<(Pubkey, AccountSharedData), SimulatorError> {
+    let pubkey_str = acc["pubkey"]
+        .as_str()
+        .ok_or_else(|| SimulatorError::MissingField {
+            pubkey: format!("#{index}"),
+            field: "pubkey",
+        })?;
+    let invalid = |field: &'static str, reason: String| SimulatorError::InvalidField {
+        pubkey: pubkey_str.to_string(),
+        field,
+        reason,
+    };
+    let missing = |field: &'static str| SimulatorError::MissingField {
+        pubkey: pubkey_str.to_string(),
+        field,
+    };
+
+    let pubkey = Pubkey::from_str(pubkey_str).map_err(|err| invalid("pubkey", err.to_string()))?;
+    let account = acc["account"].as_object().ok_or_else(|| missing("account"))?;
+    let owner = account
+        .get("owner")
+        .and_then(|owner| owner.as_str())
+        .ok_or_else(|| missing("owner"))?;
+    let data = account
+        .get("data")
+        .and_then(|data| data.as_array())
+        .and_then(|data| data.first())
+        .and_then(|data| data.as_str())
+        .ok_or_else(|| missing("data"))?;
+    let acc_data = AccountSharedData::from(Account {
+        lamports: account
+            .get("lamports")
+            .and_then(|lamports| lamports.as_u64())
+            .ok_or_else(|| missing("lamports"))?,
+        data: BASE64_STANDARD
+            .decode(data)
+            .map_err(|err| invalid("data", err.to_string()))?,
+        owner: Pubkey::from_str(owner).map_err(|err| invalid("owner", err.to_string()))?,
+        executable: account
+            .get("executable")
+            .and_then(|executable| executable.as_bool())
+            .ok_or_else(|| missing("executable"))?,
+        rent_epoch: account
+            .get("rentEpoch")
+            .and_then(|rent_epoch| rent_epoch.as_u64())
+            .ok_or_else(|| missing("rentEpoch"))?,
+    });
+    Ok((pubkey, acc_data))
+}
+
 impl AddressLoader for Simulator {
     fn load_addresses(
         self,
-        _lookups: &[MessageAddressTableLookup],
+        lookups: &[MessageAddressTableLookup],
     ) -> Result<LoadedAddresses, AddressLoaderError> {
-        Ok(LoadedAddresses {
-            writable: vec![],
-            readonly: vec![],
+        load_addresses(lookups, self.clock.slot, |pubkey| self.get_account(pubkey))
+    }
+}
+
+pub(crate) fn load_addresses(
+    lookups: &[MessageAddressTableLookup],
+    current_slot: Slot,
+    get_account: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> Result<LoadedAddresses, AddressLoaderError> {
+    let slot_hashes = get_account(&SlotHashes::id())
+        .and_then(|account| bincode::deserialize::<SlotHashes>(account.data()).ok())
+        .unwrap_or_default();
+
+    lookups
+        .iter()
+        .map(|lookup| {
+            load_lookup_table_addresses(lookup, current_slot, &slot_hashes, &get_account)
+                .map_err(into_address_loader_error)
         })
+        .collect()
+}
+
+/// Resolves the addresses referenced by a lookup, treating deactivated tables
+/// as missing the same way the bank does.
+fn load_lookup_table_addresses(
+    lookup: &MessageAddressTableLookup,
+    current_slot: Slot,
+    slot_hashes: &SlotHashes,
+    get_account: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> Result<LoadedAddresses, AddressLookupError> {
+    let table_account =
+        get_account(&lookup.account_key).ok_or(AddressLookupError::LookupTableAccountNotFound)?;
+    if table_account.owner() != &address_lookup_table_program::id() {
+        return Err(AddressLookupError::InvalidAccountOwner);
+    }
+
+    let lookup_table = AddressLookupTable::deserialize(table_account.data())
+        .map_err(|_| AddressLookupError::InvalidAccountData)?;
+    Ok(LoadedAddresses {
+        writable: lookup_table.lookup(current_slot, &lookup.writable_indexes, slot_hashes)?,
+        readonly: lookup_table.lookup(current_slot, &lookup.readonly_indexes, slot_hashes)?,
+    })
+}
+
+/// Rejects execution at `slot` when a lookup table in `accounts` was extended at
+/// or after it: the bank would ignore the addresses of that extension.
+fn check_lookup_tables(
+    accounts: &HashMap<Pubkey, AccountSharedData>,
+    slot: Slot,
+) -> Result<(), SimulatorError> {
+    for (pubkey, account) in accounts {
+        if account.owner() != &address_lookup_table_program::id() {
+            continue;
+        }
+        let Ok(lookup_table) = AddressLookupTable::deserialize(account.data()) else {
+            continue;
+        };
+        let last_extended_slot = lookup_table.meta.last_extended_slot;
+        if !lookup_table.addresses.is_empty() && slot <= last_extended_slot {
+            return Err(SimulatorError::InvalidConfig(format!(
+                "lookup table {pubkey} was last extended at slot {last_extended_slot}, \
+                 so `clock.slot` must be greater than it, not {slot}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn into_address_loader_error(err: AddressLookupError) -> AddressLoaderError {
+    match err {
+        AddressLookupError::LookupTableAccountNotFound => {
+            AddressLoaderError::LookupTableAccountNotFound
+        }
+        AddressLookupError::InvalidAccountOwner => AddressLoaderError::InvalidAccountOwner,
+        AddressLookupError::InvalidAccountData => AddressLoaderError::InvalidAccountData,
+        AddressLookupError::InvalidLookupIndex => AddressLoaderError::InvalidLookupIndex,
     }
 }
 
 pub struct TransactionSimulationResult {
     pub result: solana_sdk::transaction::Result<()>,
     pub logs: TransactionLogMessages,
+    /// State of every account key before execution, default for accounts that don't exist.
+    pub pre_simulation_accounts: Vec<TransactionAccount>,
     pub post_simulation_accounts: Vec<TransactionAccount>,
     pub units_consumed: u64,
+    /// Per-instruction breakdown of `units_consumed`, one entry per top-level instruction.
+    pub compute_units: Vec<InstructionComputeUnits>,
+    pub loaded_accounts_data_size: u32,
+    /// Total fee charged to the fee payer, including any prioritization fee.
+    pub fee: u64,
     pub return_data: Option<TransactionReturnData>,
     pub inner_instructions: Option<Vec<InnerInstructions>>,
+    /// Lamports of every account key before execution.
+    pub pre_balances: Vec<u64>,
+    /// Lamports of every account key once the transaction is committed. Failed
+    /// transactions only charge the fee payer (and advance the nonce account).
+    pub post_balances: Vec<u64>,
+    /// Balances of the transaction's token accounts before execution.
+    pub pre_token_balances: Vec<TransactionTokenBalance>,
+    /// Balances of the transaction's token accounts once the transaction is
+    /// committed, so failed transactions report their pre-execution balances.
+    pub post_token_balances: Vec<TransactionTokenBalance>,
+    /// Programs referenced by the transaction that could not be loaded.
+    pub program_diagnostics: Vec<ProgramDiagnostic>,
+    /// Accounts a bank would commit: the writable accounts on success, only the
+    /// fee payer and nonce account on failure.
+    pub committed_accounts: Vec<TransactionAccount>,
+}
+
+impl TransactionSimulationResult {
+    /// Result of a transaction rejected before it could be executed.
+    pub fn new_error(err: TransactionError) -> Self {
+        Self {
+            result: Err(err),
+            logs: vec![],
+            pre_simulation_accounts: vec![],
+            post_simulation_accounts: vec![],
+            units_consumed: 0,
+            compute_units: vec![],
+            loaded_accounts_data_size: 0,
+            fee: 0,
+            return_data: None,
+            inner_instructions: None,
+            pre_balances: vec![],
+            post_balances: vec![],
+            pre_token_balances: vec![],
+            post_token_balances: vec![],
+            program_diagnostics: vec![],
+            committed_accounts: vec![],
+        }
+    }
+
+    /// Compute units consumed by each program, excluding the units of the programs it invoked.
+    pub fn units_by_program(&self) -> HashMap<Pubkey, u64> {
+        units_by_program(&self.compute_units)
+    }
+
+    /// Changes a bank would commit to each account, for the accounts that change.
+    /// A failed transaction only changes the fee payer and nonce account.
+    pub fn account_diffs(&self) -> Vec<AccountDiff> {
+        diff_accounts(&self.pre_simulation_accounts, &self.committed_accounts)
+    }
 }
 
 pub struct LoadAndExecuteTransactionsOutput {
@@ -411,28 +1219,20 @@ impl<'a> TransactionBatch<'a> {
 
 pub struct MockBankCallback {
     pub feature_set: Arc<FeatureSet>,
+    pub base_accounts: Arc<HashMap<Pubkey, AccountSharedData>>,
+    // Accounts written on top of `base_accounts`. A zero-lamport entry hides the
+    // base account, the same way closed accounts disappear from a bank.
     pub account_shared_data: RwLock<HashMap<Pubkey, AccountSharedData>>,
 }
 
 impl TransactionProcessingCallback for MockBankCallback {
     fn account_matches_owners(&self, account: &Pubkey, owners: &[Pubkey]) -> Option<usize> {
-        if let Some(data) = self.account_shared_data.read().unwrap().get(account) {
-            if data.lamports() == 0 {
-                None
-            } else {
-                owners.iter().position(|entry| data.owner() == entry)
-            }
-        } else {
-            None
-        }
+        self.with_account(account, |data| owners.iter().position(|entry| data.owner() == entry))
+            .flatten()
     }
 
     fn get_account_shared_data(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
-        self.account_shared_data
-            .read()
-            .unwrap()
-            .get(pubkey)
-            .cloned()
+        self.with_account(pubkey, AccountSharedData::clone)
     }
 
     fn add_builtin_account(&self, name: &str, program_id: &Pubkey) {
@@ -443,88 +1243,332 @@ impl TransactionProcessingCallback for MockBankCallback {
             .unwrap()
             .insert(*program_id, account_data);
     }
+
+    fn calculate_fee(
+        &self,
+        message: &impl SVMMessage,
+        lamports_per_signature: u64,
+        prioritization_fee: u64,
+        feature_set: &FeatureSet,
+    ) -> FeeDetails {
+        solana_fee::calculate_fee_details(
+            message,
+            false,
+            lamports_per_signature,
+            prioritization_fee,
+            FeeFeatures::from(feature_set),
+        )
+    }
 }
 
 impl MockBankCallback {
-    pub fn new(account_map: Vec<(Pubkey, AccountSharedData)>) -> Self {
+    pub fn new(
+        base_accounts: Arc<HashMap<Pubkey, AccountSharedData>>,
+        feature_set: Arc<FeatureSet>,
+    ) -> Self {
         Self {
-            feature_set: Arc::new(FeatureSet::all_enabled()),
-            account_shared_data: RwLock::new(HashMap::from_iter(account_map)),
+            feature_set,
+            base_accounts,
+            account_shared_data: RwLock::new(HashMap::new()),
         }
     }
+
+    fn with_account<R>(
+        &self,
+        pubkey: &Pubkey,
+        f: impl FnOnce(&AccountSharedData) -> R,
+    ) -> Option<R> {
+        let overlay = self.account_shared_data.read().unwrap();
+        let account = match overlay.get(pubkey) {
+            Some(account) => account,
+            None => self.base_accounts.get(pubkey)?,
+        };
+        (account.lamports() != 0).then(|| f(account))
+    }
+
+    /// Merges the overlay into the base accounts, dropping closed accounts.
+    pub fn into_accounts(self) -> HashMap<Pubkey, AccountSharedData> {
+        let mut accounts = Arc::unwrap_or_clone(self.base_accounts);
+        for (pubkey, account) in self.account_shared_data.into_inner().unwrap() {
+            if account.lamports() == 0 {
+                accounts.remove(&pubkey);
+            } else {
+                accounts.insert(pubkey, account);
+            }
+        }
+        accounts
+    }
 }
 
 pub fn create_executable_environment(
     fork_graph: Arc<RwLock<MockForkGraph>>,
-    account_keys: &AccountKeys,
-    mock_bank: &mut MockBankCallback,
+    mock_bank: &MockBankCallback,
     transaction_processor: &TransactionBatchProcessor<MockForkGraph>,
-) {
-    let mut program_cache = transaction_processor.program_cache.write().unwrap();
-    let program_runtime_environment =
-        create_program_runtime_environment_v1(&mock_bank.feature_set, &ComputeBudget::default(), true, false)
-            .unwrap();
-
-    program_cache.environments = ProgramRuntimeEnvironments {
-        program_runtime_v1: Arc::new(program_runtime_environment),
-        // We are not using program runtime v2
-        program_runtime_v2: Arc::new(BuiltinProgram::new_loader(Config::default())),
-    };
+    clock: &Clock,
+    compute_budget: &ComputeBudget,
+) -> Result<(), SimulatorError> {
+    {
+        let mut program_cache = transaction_processor.program_cache.write().unwrap();
+        let program_runtime_environment = create_program_runtime_environment_v1(
+            &mock_bank.feature_set,
+            compute_budget,
+            true,
+            false,
+        )
+        .map_err(|err| SimulatorError::RuntimeEnvironment(err.to_string()))?;
 
-    program_cache.fork_graph = Some(Arc::downgrade(&fork_graph));
+        program_cache.environments = ProgramRuntimeEnvironments {
+            program_runtime_v1: Arc::new(program_runtime_environment),
+            // We are not using program runtime v2
+            program_runtime_v2: Arc::new(BuiltinProgram::new_loader(Config::default())),
+        };
 
-    // add programs to cache
-    for key in account_keys.iter() {
-        if let Some(account) = mock_bank.get_account_shared_data(key) {
-            if account.executable() && *account.owner() == solana_sdk::bpf_loader_upgradeable::id()
-            {
-                let data = account.data();
-                let program_data_account_key = Pubkey::try_from(data[4..].to_vec()).unwrap();
-                let program_data_account = mock_bank
-                    .get_account_shared_data(&program_data_account_key)
-                    .unwrap();
-                let program_data = program_data_account.data();
-                let elf_bytes = program_data[45..].to_vec();
-
-                let program_runtime_environment =
-                    program_cache.environments.program_runtime_v1.clone();
-
-                program_cache.assign_program(
-                    *key,
-                    Arc::new(
-                        ProgramCacheEntry::new(
-                            &solana_sdk::bpf_loader_upgradeable::id(),
-                            program_runtime_environment,
-                            0,
-                            0,
-                            &elf_bytes,
-                            elf_bytes.len(),
-                            &mut LoadProgramMetrics::default(),
-                        ).unwrap(),
-                    ),
-                );
-            }
+        program_cache.fork_graph = Some(Arc::downgrade(&fork_graph));
+    }
+
+    // Register the builtins a bank would for this feature set. Builtins migrated
+    // to Core BPF are still registered when the accounts don't include their BPF
+    // program, so that they keep working with every feature enabled.
+    for builtin in BUILTINS {
+        let migrated = builtin
+            .core_bpf_migration_config
+            .as_ref()
+            .is_some_and(|config| mock_bank.feature_set.is_active(&config.feature_id))
+            && mock_bank
+                .base_accounts
+                .get(&builtin.program_id)
+                .is_some_and(|account| bpf_loader_upgradeable::check_id(account.owner()));
+        let enabled = builtin
+            .enable_feature_id
+            .is_none_or(|feature_id| mock_bank.feature_set.is_active(&feature_id));
+        if enabled && !migrated {
+            transaction_processor.add_builtin(
+                mock_bank,
+                builtin.program_id,
+                builtin.name,
+                ProgramCacheEntry::new_builtin(0, builtin.name.len(), builtin.entrypoint),
+            );
         }
     }
 
-    // We must fill in the sysvar cache entries
-    let time_now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs() as i64;
-    let clock = Clock {
-        slot: DEPLOYMENT_SLOT,
-        epoch_start_timestamp: time_now.saturating_sub(10) as UnixTimestamp,
-        epoch: DEPLOYMENT_EPOCH,
-        leader_schedule_epoch: DEPLOYMENT_EPOCH,
-        unix_timestamp: time_now as UnixTimestamp,
-    };
+    // Precompiles are executed by the runtime, but their program accounts must exist.
+    for precompile in get_precompiles() {
+        let enabled = precompile
+            .feature
+            .is_none_or(|feature_id| mock_bank.feature_set.is_active(&feature_id));
+        if enabled && !mock_bank.base_accounts.contains_key(&precompile.program_id) {
+            mock_bank.account_shared_data.write().unwrap().insert(
+                precompile.program_id,
+                native_loader::create_loadable_account_with_fields("", (1, 0)),
+            );
+        }
+    }
 
-    let mut account_data = AccountSharedData::default();
-    account_data.set_data_from_slice(bincode::serialize(&clock).unwrap().as_slice());
+    // We must fill in the sysvar cache entries
+    let account_data = create_account_shared_data_with_fields(clock, (1, 0));
     mock_bank
         .account_shared_data
         .write()
         .unwrap()
         .insert(Clock::id(), account_data);
-}
\ No newline at end of file
+
+    transaction_processor.fill_missing_sysvar_cache_entries(mock_bank);
+
+    Ok(())
+}
+
+fn insert_missing_sysvar<S: Sysvar>(mock_bank: &MockBankCallback, sysvar: &S) {
+    if mock_bank.get_account_shared_data(&S::id()).is_none() {
+        mock_bank
+            .account_shared_data
+            .write()
+            .unwrap()
+            .insert(S::id(), create_account_shared_data_with_fields(sysvar, (1, 0)));
+    }
+}
+
+/// How `load_programs` treats an account a transaction references.
+enum ProgramLoad {
+    /// The account isn't a program the simulator compiles.
+    Skip,
+    /// The program can't be executed. It is evicted from the program cache, so
+    /// the SVM fails transactions invoking it the same way a validator would.
+    Evict(ProgramDiagnostic),
+    /// The program's ELF starts at `elf_offset` in `program_data_account`.
+    Compile {
+        loader: Pubkey,
+        program_data_account: AccountSharedData,
+        elf_offset: usize,
+    },
+}
+
+impl ProgramLoad {
+    fn new(key: &Pubkey, account: AccountSharedData, mock_bank: &MockBankCallback) -> Self {
+        let loader = *account.owner();
+        let diagnostic = |reason: &str| ProgramDiagnostic {
+            program_id: *key,
+            reason: reason.to_string(),
+        };
+        if loader == bpf_loader_upgradeable::id() {
+            if !account.executable() {
+                return Self::Skip;
+            }
+            match load_upgradeable_program(key, &account, |address| {
+                mock_bank.get_account_shared_data(address)
+            }) {
+                Ok((_, program_data_account)) => Self::Compile {
+                    loader,
+                    program_data_account,
+                    elf_offset: UpgradeableLoaderState::size_of_programdata_metadata(),
+                },
+                Err(diagnostic) => Self::Evict(diagnostic),
+            }
+        } else if loader == bpf_loader::id() || loader == bpf_loader_deprecated::id() {
+            if !account.executable() {
+                return Self::Skip;
+            }
+            Self::Compile {
+                loader,
+                program_data_account: account,
+                elf_offset: 0,
+            }
+        } else if loader == loader_v4::id() {
+            match solana_loader_v4_program::get_state(account.data()) {
+                Ok(state) if matches!(state.status, LoaderV4Status::Retracted) => {
+                    Self::Evict(diagnostic("retracted"))
+                }
+                Ok(_) => Self::Compile {
+                    loader,
+                    program_data_account: account,
+                    elf_offset: LoaderV4State::program_data_offset(),
+                },
+                Err(err) => Self::Evict(diagnostic(&format!("invalid program account: {err}"))),
+            }
+        } else {
+            Self::Skip
+        }
+    }
+}
+
+/// Resolves the programs among `keys` against `mock_bank`'s accounts.
+fn program_loads<'a>(
+    keys: impl Iterator<Item = &'a Pubkey>,
+    mock_bank: &MockBankCallback,
+) -> Vec<(Pubkey, ProgramLoad)> {
+    keys.filter_map(|key| {
+        let account = mock_bank.get_account_shared_data(key)?;
+        Some((*key, ProgramLoad::new(key, account, mock_bank)))
+    })
+    .collect()
+}
+
+/// A program compiled into the program cache.
+struct LoadedProgram {
+    program_data_account: AccountSharedData,
+    /// Why the program failed verification, leaving a tombstone in the cache.
+    diagnostic: Option<ProgramDiagnostic>,
+}
+
+/// Whether the program cache already holds exactly the programs in `loads`.
+fn is_loaded(
+    loads: &[(Pubkey, ProgramLoad)],
+    loaded_programs: &HashMap<Pubkey, LoadedProgram>,
+) -> bool {
+    loads.iter().all(|(key, load)| match load {
+        ProgramLoad::Skip => true,
+        ProgramLoad::Evict(_) => !loaded_programs.contains_key(key),
+        ProgramLoad::Compile {
+            program_data_account,
+            ..
+        } => loaded_programs.get(key).is_some_and(|loaded| {
+            loaded.program_data_account.data() == program_data_account.data()
+        }),
+    })
+}
+
+fn program_diagnostics(
+    loads: &[(Pubkey, ProgramLoad)],
+    loaded_programs: &HashMap<Pubkey, LoadedProgram>,
+) -> Vec<ProgramDiagnostic> {
+    loads
+        .iter()
+        .filter_map(|(key, load)| match load {
+            ProgramLoad::Skip => None,
+            ProgramLoad::Evict(diagnostic) => Some(diagnostic.clone()),
+            ProgramLoad::Compile { .. } => loaded_programs.get(key)?.diagnostic.clone(),
+        })
+        .collect()
+}
+
+/// Updates the program cache to hold the programs in `loads`. Programs whose
+/// program data is unchanged since they were last compiled are skipped, and
+/// programs failing verification are replaced by a tombstone.
+fn load_programs(
+    loads: &[(Pubkey, ProgramLoad)],
+    transaction_processor: &TransactionBatchProcessor<MockForkGraph>,
+    loaded_programs: &mut HashMap<Pubkey, LoadedProgram>,
+) {
+    let mut program_cache = transaction_processor.program_cache.write().unwrap();
+
+    for (key, load) in loads {
+        let (loader, program_data_account, elf_offset) = match load {
+            ProgramLoad::Skip => continue,
+            ProgramLoad::Evict(_) => {
+                if loaded_programs.remove(key).is_some() {
+                    program_cache.remove_programs(std::iter::once(*key));
+                }
+                continue;
+            }
+            ProgramLoad::Compile {
+                loader,
+                program_data_account,
+                elf_offset,
+            } => (loader, program_data_account, *elf_offset),
+        };
+        if loaded_programs.get(key).is_some_and(|loaded| {
+            loaded.program_data_account.data() == program_data_account.data()
+        }) {
+            continue;
+        }
+
+        let program_runtime_environment = program_cache.environments.program_runtime_v1.clone();
+        let entry = match program_data_account.data().get(elf_offset..) {
+            Some(elf_bytes) => ProgramCacheEntry::new(
+                loader,
+                program_runtime_environment.clone(),
+                DEPLOYMENT_SLOT,
+                DEPLOYMENT_SLOT,
+                elf_bytes,
+                elf_bytes.len(),
+                &mut LoadProgramMetrics::default(),
+            )
+            .map_err(|err| err.to_string()),
+            None => Err("invalid program data account".to_string()),
+        };
+        let (entry, diagnostic) = match entry {
+            Ok(entry) => (entry, None),
+            Err(reason) => (
+                ProgramCacheEntry::new_tombstone(
+                    DEPLOYMENT_SLOT,
+                    ProgramCacheEntryOwner::try_from(loader).unwrap_or_default(),
+                    ProgramCacheEntryType::FailedVerification(program_runtime_environment),
+                ),
+                Some(ProgramDiagnostic {
+                    program_id: *key,
+                    reason,
+                }),
+            ),
+        };
+
+        // The cache refuses to replace a loaded entry in place.
+        program_cache.remove_programs(std::iter::once(*key));
+        program_cache.assign_program(*key, Arc::new(entry));
+        loaded_programs.insert(
+            *key,
+            LoadedProgram {
+                program_data_account: program_data_account.clone(),
+                diagnostic,
+            },
+        );
+    }
+}
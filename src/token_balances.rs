@@ -0,0 +1,237 @@
+use {
+    solana_account_decoder::{
+        parse_account_data::SplTokenAdditionalDataV2,
+        parse_token::{is_known_spl_token_id, token_amount_to_ui_amount_v3, UiTokenAmount},
+    },
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        clock::UnixTimestamp,
+        message::SanitizedMessage,
+        pubkey::Pubkey,
+    },
+    spl_token_2022::{
+        extension::{
+            interest_bearing_mint::InterestBearingConfig, scaled_ui_amount::ScaledUiAmountConfig,
+            BaseStateWithExtensions, StateWithExtensions,
+        },
+        state::{Account, Mint},
+    },
+};
+
+/// Balance of a token account, as reported in the `preTokenBalances` and
+/// `postTokenBalances` of `getTransaction`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionTokenBalance {
+    /// Index of the token account in the transaction's account keys.
+    pub account_index: u8,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    /// SPL Token or Token-2022.
+    pub program_id: Pubkey,
+    pub ui_token_amount: UiTokenAmount,
+}
+
+/// Balances of the initialized token accounts among `message`'s account keys,
+/// reading accounts and their mints through `get_account`. Interest-bearing and
+/// scaled UI amounts are computed at `unix_timestamp`.
+pub(crate) fn collect_token_balances(
+    message: &SanitizedMessage,
+    unix_timestamp: UnixTimestamp,
+    get_account: impl Fn(&Pubkey) -> Option<AccountSharedData>,
+) -> Vec<TransactionTokenBalance> {
+    message
+        .account_keys()
+        .iter()
+        .enumerate()
+        .filter(|(index, pubkey)| !message.is_invoked(*index) && !is_known_spl_token_id(pubkey))
+        .filter_map(|(index, pubkey)| {
+            let account_index = u8::try_from(index).ok()?;
+            let account = get_account(pubkey)?;
+            let program_id = *account.owner();
+            if !is_known_spl_token_id(&program_id) {
+                return None;
+            }
+            let token_account = StateWithExtensions::<Account>::unpack(account.data()).ok()?;
+            let mint = get_account(&token_account.base.mint)?;
+            let additional_data = mint_additional_data(mint.data(), unix_timestamp)?;
+            Some(TransactionTokenBalance {
+                account_index,
+                mint: token_account.base.mint,
+                owner: token_account.base.owner,
+                program_id,
+                ui_token_amount: token_amount_to_ui_amount_v3(
+                    token_account.base.amount,
+                    &additional_data,
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Decimals and UI amount extensions of the mint with account data `data`.
+pub(crate) fn mint_additional_data(
+    data: &[u8],
+    unix_timestamp: UnixTimestamp,
+) -> Option<SplTokenAdditionalDataV2> {
+    let mint = StateWithExtensions::<Mint>::unpack(data).ok()?;
+    Some(SplTokenAdditionalDataV2 {
+        decimals: mint.base.decimals,
+        interest_bearing_config: mint
+            .get_extension::<InterestBearingConfig>()
+            .ok()
+            .map(|config| (*config, unix_timestamp)),
+        scaled_ui_amount_config: mint
+            .get_extension::<ScaledUiAmountConfig>()
+            .ok()
+            .map(|config| (*config, unix_timestamp)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{
+            instruction::{AccountMeta, Instruction},
+            message::Message,
+            program_option::COption,
+            program_pack::Pack,
+        },
+        spl_token_2022::{
+            extension::{BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut},
+            state::AccountState,
+        },
+        std::collections::{HashMap, HashSet},
+    };
+
+    fn scaled_mint_data(decimals: u8) -> Vec<u8> {
+        let len =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::ScaledUiAmount])
+                .unwrap();
+        let mut data = vec![0; len];
+        let mut mint = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        let config = mint.init_extension::<ScaledUiAmountConfig>(true).unwrap();
+        config.multiplier = 2.0.into();
+        config.new_multiplier_effective_timestamp = 1_000.into();
+        config.new_multiplier = 3.0.into();
+        mint.base = Mint {
+            decimals,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+
+    fn token_account_data(mint: Pubkey, owner: Pubkey, amount: u64) -> Vec<u8> {
+        let mut data = vec![0; Account::LEN];
+        Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    #[test]
+    fn test_mint_additional_data() {
+        let mut data = vec![0; Mint::LEN];
+        Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        }
+        .pack_into_slice(&mut data);
+        let additional_data = mint_additional_data(&data, 0).unwrap();
+        assert_eq!(additional_data.decimals, 6);
+        assert!(additional_data.interest_bearing_config.is_none());
+        assert!(additional_data.scaled_ui_amount_config.is_none());
+
+        let additional_data = mint_additional_data(&scaled_mint_data(2), 500).unwrap();
+        assert_eq!(additional_data.decimals, 2);
+        assert_eq!(
+            additional_data.scaled_ui_amount_config.map(|(_, ts)| ts),
+            Some(500)
+        );
+
+        assert!(mint_additional_data(&[0; 10], 0).is_none());
+    }
+
+    #[test]
+    fn test_collect_token_balances() {
+        let payer = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let unknown_mint_account = Pubkey::new_unique();
+        let accounts = HashMap::from([
+            (
+                mint,
+                AccountSharedData::from(solana_sdk::account::Account {
+                    lamports: 1,
+                    data: scaled_mint_data(2),
+                    owner: spl_token_2022::id(),
+                    ..Default::default()
+                }),
+            ),
+            (
+                token_account,
+                AccountSharedData::from(solana_sdk::account::Account {
+                    lamports: 1,
+                    data: token_account_data(mint, owner, 150),
+                    owner: spl_token_2022::id(),
+                    ..Default::default()
+                }),
+            ),
+            (
+                unknown_mint_account,
+                AccountSharedData::from(solana_sdk::account::Account {
+                    lamports: 1,
+                    data: token_account_data(Pubkey::new_unique(), owner, 1),
+                    owner: spl_token_2022::id(),
+                    ..Default::default()
+                }),
+            ),
+        ]);
+        let instruction = Instruction::new_with_bytes(
+            spl_token_2022::id(),
+            &[],
+            vec![
+                AccountMeta::new(token_account, false),
+                AccountMeta::new(unknown_mint_account, false),
+            ],
+        );
+        let message = SanitizedMessage::try_from_legacy_message(
+            Message::new(&[instruction], Some(&payer)),
+            &HashSet::new(),
+        )
+        .unwrap();
+        let get_account = |pubkey: &Pubkey| accounts.get(pubkey).cloned();
+
+        let balances = collect_token_balances(&message, 0, get_account);
+        assert_eq!(balances.len(), 1);
+        let balance = &balances[0];
+        assert_eq!(
+            message.account_keys()[balance.account_index.into()],
+            token_account
+        );
+        assert_eq!(balance.mint, mint);
+        assert_eq!(balance.owner, owner);
+        assert_eq!(balance.program_id, spl_token_2022::id());
+        assert_eq!(balance.ui_token_amount.amount, "150");
+        assert_eq!(balance.ui_token_amount.decimals, 2);
+        assert_eq!(balance.ui_token_amount.ui_amount_string, "3");
+
+        // The new multiplier is in effect.
+        let balances = collect_token_balances(&message, 1_000, get_account);
+        assert_eq!(balances[0].ui_token_amount.ui_amount_string, "4.5");
+        assert_eq!(balances[0].ui_token_amount.ui_amount, Some(4.5));
+    }
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use {
+    common::{system_account, token_program_elf, transaction, upgradeable_program_accounts},
+    solana_address_lookup_table_interface::instruction::create_lookup_table,
+    solana_sdk::{
+        account::AccountSharedData,
+        clock::Slot,
+        compute_budget::ComputeBudgetInstruction,
+        ed25519_program,
+        feature_set::migrate_stake_program_to_core_bpf,
+        hash::Hash,
+        instruction::{Instruction, InstructionError},
+        native_loader,
+        precompiles::PrecompileError,
+        pubkey,
+        pubkey::Pubkey,
+        secp256k1_program,
+        signature::{Keypair, Signer},
+        stake::{
+            self,
+            state::{Authorized, Lockup},
+        },
+        transaction::TransactionError,
+    },
+    solana_simulate::{FeatureSetConfig, Simulator, SimulatorConfig, TransactionSimulationResult},
+};
+
+const SECP256R1_PROGRAM: Pubkey = pubkey!("Secp256r1SigVerify1111111111111111111111111");
+const PAYER_LAMPORTS: u64 = 10_000_000_000;
+/// A slot in the `SlotHashes` sysvar, which lookup tables are derived from.
+const RECENT_SLOT: Slot = 4;
+
+fn simulator(accounts: Vec<(Pubkey, AccountSharedData)>) -> Simulator {
+    Simulator::try_new_from_accounts(
+        SimulatorConfig {
+            slot_hashes: Some(vec![(RECENT_SLOT, Hash::new_unique())]),
+            ..SimulatorConfig::default()
+        },
+        accounts,
+    )
+    .unwrap()
+}
+
+fn simulate(
+    simulator: &Simulator,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+) -> TransactionSimulationResult {
+    simulator.simulate_transaction_unchecked(&transaction(instructions, payer), false)
+}
+
+fn create_stake_account(payer: &Pubkey) -> Vec<Instruction> {
+    stake::instruction::create_account(
+        payer,
+        &Keypair::new().pubkey(),
+        &Authorized::auto(payer),
+        &Lockup::default(),
+        10_000_000,
+    )
+}
+
+/// Whether the program logged BPF compute unit usage, which builtins don't.
+fn ran_as_bpf(result: &TransactionSimulationResult, program_id: &Pubkey) -> bool {
+    let prefix = format!("Program {program_id} consumed ");
+    result.logs.iter().any(|log| log.starts_with(&prefix))
+}
+
+#[test]
+fn test_builtins() {
+    let payer = Pubkey::new_unique();
+    let simulator = simulator(vec![(payer, system_account(PAYER_LAMPORTS))]);
+
+    let (instruction, _) = create_lookup_table(payer, payer, RECENT_SLOT);
+    let result = simulate(&simulator, &[instruction], &payer);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+
+    let result = simulate(&simulator, &create_stake_account(&payer), &payer);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert!(!ran_as_bpf(&result, &stake::program::id()));
+
+    let result = simulate(
+        &simulator,
+        &[ComputeBudgetInstruction::set_compute_unit_limit(100_000)],
+        &payer,
+    );
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+}
+
+#[test]
+fn test_precompiles() {
+    let payer = Pubkey::new_unique();
+    let simulator = simulator(vec![(payer, system_account(PAYER_LAMPORTS))]);
+
+    // Precompile instructions without signatures are valid.
+    for (program_id, data) in [
+        (ed25519_program::id(), vec![0, 0]),
+        (secp256k1_program::id(), vec![0]),
+    ] {
+        let instruction = Instruction::new_with_bytes(program_id, &data, vec![]);
+        let result = simulate(&simulator, &[instruction], &payer);
+        assert_eq!(result.result, Ok(()), "{program_id}: {:?}", result.logs);
+    }
+
+    // secp256r1 requires at least one signature.
+    let instruction = Instruction::new_with_bytes(SECP256R1_PROGRAM, &[0, 0], vec![]);
+    let result = simulate(&simulator, &[instruction], &payer);
+    assert_eq!(
+        result.result,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(PrecompileError::InvalidInstructionDataSize as u32)
+        ))
+    );
+}
+
+#[test]
+fn test_migrated_builtins_with_native_accounts() {
+    let payer = Pubkey::new_unique();
+    // Accounts fetched before the migration still hold the native program.
+    let simulator = simulator(vec![
+        (payer, system_account(PAYER_LAMPORTS)),
+        (
+            stake::program::id(),
+            native_loader::create_loadable_account_with_fields("solana_stake_program", (1, 0)),
+        ),
+        (
+            solana_address_lookup_table_interface::program::id(),
+            native_loader::create_loadable_account_with_fields(
+                "address_lookup_table_program",
+                (1, 0),
+            ),
+        ),
+    ]);
+
+    let (instruction, _) = create_lookup_table(payer, payer, RECENT_SLOT);
+    let result = simulate(&simulator, &[instruction], &payer);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+
+    let result = simulate(&simulator, &create_stake_account(&payer), &payer);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert!(!ran_as_bpf(&result, &stake::program::id()));
+}
+
+#[test]
+fn test_migrated_builtin_loaded_from_accounts() {
+    let payer = Pubkey::new_unique();
+    let mut accounts = vec![(payer, system_account(PAYER_LAMPORTS))];
+    // Any BPF program stands in for the Core BPF stake program.
+    accounts.extend(upgradeable_program_accounts(
+        stake::program::id(),
+        &token_program_elf(),
+    ));
+    let instructions = [Instruction::new_with_bytes(
+        stake::program::id(),
+        &[],
+        vec![],
+    )];
+
+    let simulator = Simulator::new_with_accounts(accounts.clone());
+    let result = simulate(&simulator, &instructions, &payer);
+    assert!(
+        ran_as_bpf(&result, &stake::program::id()),
+        "{:?}",
+        result.logs
+    );
+
+    // Until the migration is active, the builtin takes precedence.
+    let simulator = Simulator::try_new_from_accounts(
+        SimulatorConfig {
+            feature_set: FeatureSetConfig::AllEnabledExcept(vec![
+                migrate_stake_program_to_core_bpf::id(),
+            ]),
+            ..SimulatorConfig::default()
+        },
+        accounts,
+    )
+    .unwrap();
+    let result = simulate(&simulator, &instructions, &payer);
+    assert!(
+        !ran_as_bpf(&result, &stake::program::id()),
+        "{:?}",
+        result.logs
+    );
+}
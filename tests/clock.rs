@@ -0,0 +1,65 @@
+use {
+    solana_sdk::{
+        account::create_account_shared_data_for_test, clock::Clock, epoch_schedule::EpochSchedule,
+        sysvar,
+    },
+    solana_simulate::{ClockConfig, Simulator, SimulatorConfig},
+};
+
+fn simulator(clock: ClockConfig) -> Simulator {
+    Simulator::try_new_from_accounts(
+        SimulatorConfig {
+            clock,
+            ..SimulatorConfig::default()
+        },
+        vec![],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_clock_defaults() {
+    let epoch_schedule = EpochSchedule::default();
+    let slot = 300_000_000;
+    let simulator = simulator(ClockConfig {
+        slot: Some(slot),
+        unix_timestamp: Some(1_700_000_000),
+        ..ClockConfig::default()
+    });
+
+    let clock = simulator.clock();
+    assert_eq!(clock.slot, slot);
+    assert_eq!(clock.epoch, epoch_schedule.get_epoch(slot));
+    assert_eq!(
+        clock.leader_schedule_epoch,
+        epoch_schedule.get_leader_schedule_epoch(slot)
+    );
+    assert_eq!(clock.unix_timestamp, 1_700_000_000);
+    assert_eq!(simulator.get_sysvar::<Clock>().as_ref(), Some(clock));
+}
+
+#[test]
+fn test_leader_schedule_epoch() {
+    // Leader schedules computed two epochs ahead.
+    let epoch_schedule = EpochSchedule::custom(8192, 2 * 8192, false);
+    let slot = 10 * 8192 + 100;
+    let simulator = Simulator::try_new_from_accounts(
+        SimulatorConfig {
+            clock: ClockConfig {
+                slot: Some(slot),
+                unix_timestamp: Some(1_700_000_000),
+                ..ClockConfig::default()
+            },
+            ..SimulatorConfig::default()
+        },
+        vec![(
+            sysvar::epoch_schedule::id(),
+            create_account_shared_data_for_test(&epoch_schedule),
+        )],
+    )
+    .unwrap();
+
+    let clock = simulator.clock();
+    assert_eq!(clock.epoch, 10);
+    assert_eq!(clock.leader_schedule_epoch, 12);
+}
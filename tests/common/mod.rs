@@ -0,0 +1,229 @@
+// Shared by the integration tests; each test crate uses a subset.
+#![allow(dead_code)]
+
+use {
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount, WritableAccount},
+        bpf_loader_upgradeable::{self, get_program_data_address, UpgradeableLoaderState},
+        bs58,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        pubkey,
+        pubkey::Pubkey,
+        system_program,
+        transaction::{MessageHash, SanitizedTransaction, Transaction},
+    },
+    solana_simulate::{Simulator, SimulatorConfig},
+    std::{collections::HashSet, path::PathBuf},
+};
+
+pub const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const RAYDIUM_AMM: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+pub const SWAP_PAYER: Pubkey = pubkey!("H7GCUaJMUgdQiNYyoQTTmwG4fSYMV8W8ECmATZ2kyNTJ");
+const SWAP_WSOL_ACCOUNT: Pubkey = pubkey!("JBxmvDYWetwnND8z1ppEuVWpXjpds77J2DgR2hD4Qmhg");
+
+pub fn system_account(lamports: u64) -> AccountSharedData {
+    AccountSharedData::new(lamports, 0, &system_program::id())
+}
+
+/// An unsigned legacy transaction paid by `payer`.
+pub fn transaction(instructions: &[Instruction], payer: &Pubkey) -> SanitizedTransaction {
+    SanitizedTransaction::try_from_legacy_transaction(
+        Transaction::new_unsigned(Message::new(instructions, Some(payer))),
+        &HashSet::new(),
+    )
+    .unwrap()
+}
+
+pub fn accounts_file_config() -> SimulatorConfig {
+    SimulatorConfig {
+        accounts_path: PathBuf::from("./accounts.json"),
+        ..SimulatorConfig::default()
+    }
+}
+
+/// A simulator over the accounts of the bundled Raydium swap.
+pub fn accounts_file_simulator() -> Simulator {
+    Simulator::try_new(accounts_file_config()).unwrap()
+}
+
+/// The instructions of the bundled Raydium swap: wrap SOL, swap it through the
+/// Raydium AMM and close the wrapped SOL account.
+pub fn swap_instructions() -> Vec<Instruction> {
+    let amm_accounts = [
+        (TOKEN_PROGRAM, false),
+        (
+            pubkey!("A3TiDsQgQFKSLXcj51Jiigm4Fd4F27GGrsXAsHaXh3E1"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("EvFmWAGp82Kfenmh8xFzSBGYChtmWXmqqTK9QSWW9BqB"),
+            true,
+        ),
+        (
+            pubkey!("A9M4vMERK54sEpGefBVnvxJhJRa9U6tUGbkYgYbjci1B"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (
+            pubkey!("EvFmWAGp82Kfenmh8xFzSBGYChtmWXmqqTK9QSWW9BqB"),
+            true,
+        ),
+        (
+            pubkey!("A9M4vMERK54sEpGefBVnvxJhJRa9U6tUGbkYgYbjci1B"),
+            true,
+        ),
+        (
+            pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1"),
+            true,
+        ),
+        (SWAP_WSOL_ACCOUNT, true),
+        (
+            pubkey!("Cg1sa7AgfqVTQYREXGv4KwB9qBq5ymNddGTd1CdShjxZ"),
+            true,
+        ),
+    ]
+    .into_iter()
+    .map(|(pubkey, is_writable)| AccountMeta {
+        pubkey,
+        is_signer: false,
+        is_writable,
+    })
+    .chain([AccountMeta::new(SWAP_PAYER, true)])
+    .collect();
+
+    vec![
+        Instruction::new_with_bytes(
+            system_program::id(),
+            &bs58::decode("3ipZX7g9NBXycb5v9QjqWwuhh8PxV9WL3HbJRPdURtmm5W1r5t7QtWMbGWB7mQgB8itRgPTMomJoFW7k4WhmYdYLDyWW5WMHN9M2TPGB2xFoTt3tkD87ECGUXNUzp7WskoNcjTtM9nVZMxZDcAGN1GAD82P9vhnSsQKiE5Kh2").into_vec().unwrap(),
+            vec![
+                AccountMeta::new(SWAP_PAYER, true),
+                AccountMeta::new(SWAP_WSOL_ACCOUNT, false),
+            ],
+        ),
+        Instruction::new_with_bytes(
+            TOKEN_PROGRAM,
+            &bs58::decode("2").into_vec().unwrap(),
+            vec![
+                AccountMeta::new(SWAP_WSOL_ACCOUNT, false),
+                AccountMeta::new_readonly(pubkey!("So11111111111111111111111111111111111111112"), false),
+                AccountMeta::new(SWAP_PAYER, true),
+                AccountMeta::new_readonly(pubkey!("SysvarRent111111111111111111111111111111111"), false),
+            ],
+        ),
+        Instruction::new_with_bytes(
+            RAYDIUM_AMM,
+            &bs58::decode("6FL8fBmJqzqeUnA28wVdrto").into_vec().unwrap(),
+            amm_accounts,
+        ),
+        Instruction::new_with_bytes(
+            TOKEN_PROGRAM,
+            &bs58::decode("A").into_vec().unwrap(),
+            vec![
+                AccountMeta::new(SWAP_WSOL_ACCOUNT, false),
+                AccountMeta::new(SWAP_PAYER, true),
+                AccountMeta::new(SWAP_PAYER, true),
+            ],
+        ),
+    ]
+}
+
+/// The bundled Raydium swap, with `instructions` prepended.
+pub fn swap_transaction_with(
+    simulator: &Simulator,
+    instructions: &[Instruction],
+) -> SanitizedTransaction {
+    let instructions = instructions
+        .iter()
+        .cloned()
+        .chain(swap_instructions())
+        .collect::<Vec<_>>();
+    SanitizedTransaction::try_create(
+        Transaction::new_unsigned(Message::new(&instructions, Some(&SWAP_PAYER))).into(),
+        MessageHash::Compute,
+        None,
+        simulator.clone(),
+        &HashSet::new(),
+    )
+    .unwrap()
+}
+
+pub fn swap_transaction(simulator: &Simulator) -> SanitizedTransaction {
+    swap_transaction_with(simulator, &[])
+}
+
+/// The ELF of the SPL Token program in the accounts file, which any loader can
+/// deploy.
+pub fn token_program_elf() -> Vec<u8> {
+    accounts_file_simulator()
+        .get_account(&TOKEN_PROGRAM)
+        .unwrap()
+        .data()
+        .to_vec()
+}
+
+/// The program and programdata accounts of `elf` deployed at `program_id` by
+/// the upgradeable BPF loader.
+pub fn upgradeable_program_accounts(
+    program_id: Pubkey,
+    elf: &[u8],
+) -> [(Pubkey, AccountSharedData); 2] {
+    let programdata_address = get_program_data_address(&program_id);
+    let mut program = AccountSharedData::new_data(
+        1_000_000_000,
+        &UpgradeableLoaderState::Program {
+            programdata_address,
+        },
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    program.set_executable(true);
+    let mut programdata = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(Pubkey::new_unique()),
+    })
+    .unwrap();
+    programdata.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+    programdata.extend_from_slice(elf);
+    let mut programdata_account = AccountSharedData::new(
+        1_000_000_000,
+        programdata.len(),
+        &bpf_loader_upgradeable::id(),
+    );
+    programdata_account.set_data_from_slice(&programdata);
+    [
+        (program_id, program),
+        (programdata_address, programdata_account),
+    ]
+}
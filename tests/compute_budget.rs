@@ -0,0 +1,86 @@
+mod common;
+
+use {
+    common::{accounts_file_config, accounts_file_simulator, swap_transaction_with},
+    solana_sdk::{compute_budget::ComputeBudgetInstruction, transaction::TransactionError},
+    solana_simulate::{Simulator, SimulatorConfig},
+};
+
+#[test]
+fn test_compute_budget_instructions() {
+    let simulator = accounts_file_simulator();
+    let transaction = swap_transaction_with(
+        &simulator,
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(1000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+        ],
+    );
+
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert!(matches!(
+        result.result,
+        Err(TransactionError::InstructionError(_, _))
+    ));
+    assert!(result.units_consumed <= 1000);
+    // The prioritization fee is charged on the requested limit.
+    assert_eq!(result.fee, 5000 + 1000);
+    assert_eq!(result.post_balances[0], result.pre_balances[0] - result.fee);
+
+    let transaction = swap_transaction_with(
+        &simulator,
+        &[ComputeBudgetInstruction::request_heap_frame(64 * 1024)],
+    );
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+}
+
+#[test]
+fn test_compute_unit_limit_override() {
+    let simulator = Simulator::try_new(SimulatorConfig {
+        compute_unit_limit: Some(1_400_000),
+        ..accounts_file_config()
+    })
+    .unwrap();
+    let transaction = swap_transaction_with(
+        &simulator,
+        &[ComputeBudgetInstruction::set_compute_unit_limit(1000)],
+    );
+
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert!(result.units_consumed > 1000);
+}
+
+#[test]
+fn test_heap_size_override() {
+    let simulator = Simulator::try_new(SimulatorConfig {
+        heap_size: Some(256 * 1024),
+        ..accounts_file_config()
+    })
+    .unwrap();
+    let result =
+        simulator.simulate_transaction_unchecked(&swap_transaction_with(&simulator, &[]), false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+
+    for heap_size in [1000, 16 * 1024, 512 * 1024] {
+        assert!(Simulator::try_new(SimulatorConfig {
+            heap_size: Some(heap_size),
+            ..accounts_file_config()
+        })
+        .is_err());
+    }
+}
+
+#[test]
+fn test_transaction_account_lock_limit() {
+    let simulator = Simulator::try_new(SimulatorConfig {
+        transaction_account_lock_limit: Some(3),
+        ..accounts_file_config()
+    })
+    .unwrap();
+
+    let result =
+        simulator.simulate_transaction_unchecked(&swap_transaction_with(&simulator, &[]), false);
+    assert_eq!(result.result, Err(TransactionError::TooManyAccountLocks));
+}
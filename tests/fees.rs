@@ -0,0 +1,72 @@
+mod common;
+
+use {
+    common::{
+        accounts_file_config, accounts_file_simulator, swap_transaction, system_account,
+        transaction,
+    },
+    solana_sdk::{
+        account::ReadableAccount, pubkey::Pubkey, system_instruction, transaction::TransactionError,
+    },
+    solana_simulate::{Simulator, SimulatorConfig},
+};
+
+#[test]
+fn test_fee_is_debited() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = Simulator::new_with_accounts(vec![(payer, system_account(10_000_000_000))]);
+
+    let result = simulator.simulate_transaction_unchecked(
+        &transaction(
+            &[system_instruction::transfer(&payer, &to, 1_000_000)],
+            &payer,
+        ),
+        false,
+    );
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert_eq!(result.fee, 5000);
+    assert_eq!(
+        result.post_simulation_accounts[0].1.lamports(),
+        10_000_000_000 - 1_000_000 - 5000
+    );
+    assert_eq!(
+        result.post_balances[0],
+        result.pre_balances[0] - 1_000_000 - 5000
+    );
+}
+
+#[test]
+fn test_lamports_per_signature() {
+    let default = accounts_file_simulator();
+    let simulator = Simulator::try_new(SimulatorConfig {
+        lamports_per_signature: Some(10_000),
+        ..accounts_file_config()
+    })
+    .unwrap();
+
+    let base = default.simulate_transaction_unchecked(&swap_transaction(&default), false);
+    let result = simulator.simulate_transaction_unchecked(&swap_transaction(&simulator), false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert_eq!(base.fee, 5000);
+    assert_eq!(result.fee, 10_000);
+    assert_eq!(result.post_balances[0], base.post_balances[0] - 5000);
+}
+
+#[test]
+fn test_insufficient_funds_for_fee() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = Simulator::new_with_accounts(vec![(payer, system_account(4000))]);
+
+    let result = simulator.simulate_transaction_unchecked(
+        &transaction(&[system_instruction::transfer(&payer, &to, 1)], &payer),
+        false,
+    );
+    assert_eq!(
+        result.result,
+        Err(TransactionError::InsufficientFundsForFee)
+    );
+    assert_eq!(result.fee, 0);
+    assert_eq!(result.post_balances, result.pre_balances);
+}
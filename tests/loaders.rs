@@ -0,0 +1,157 @@
+mod common;
+
+use {
+    common::{system_account, token_program_elf, transaction},
+    solana_sdk::{
+        account::{AccountSharedData, WritableAccount},
+        bpf_loader, bpf_loader_deprecated,
+        feature_set::enable_loader_v4,
+        instruction::{Instruction, InstructionError},
+        loader_v4::{self, LoaderV4State, LoaderV4Status},
+        pubkey::Pubkey,
+        transaction::{SanitizedTransaction, TransactionError},
+    },
+    solana_simulate::{FeatureSetConfig, Simulator, SimulatorConfig},
+    std::collections::HashMap,
+};
+
+fn program_account(loader: &Pubkey, data: &[u8]) -> AccountSharedData {
+    let mut account = AccountSharedData::new(1_000_000_000, data.len(), loader);
+    account.set_data_from_slice(data);
+    account.set_executable(true);
+    account
+}
+
+fn loader_v4_account(status: LoaderV4Status, elf: &[u8]) -> AccountSharedData {
+    // `LoaderV4State` is `repr(C)`: slot, authority, then status.
+    let mut data = Vec::with_capacity(LoaderV4State::program_data_offset() + elf.len());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(&(status as u64).to_le_bytes());
+    data.extend_from_slice(elf);
+    program_account(&loader_v4::id(), &data)
+}
+
+/// An instruction the SPL Token program rejects after running.
+fn invoke(program_id: Pubkey, payer: &Pubkey) -> SanitizedTransaction {
+    transaction(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        payer,
+    )
+}
+
+fn ran(logs: &[String], program_id: &Pubkey) -> bool {
+    let prefix = format!("Program {program_id} consumed ");
+    logs.iter().any(|log| log.starts_with(&prefix))
+}
+
+#[test]
+fn test_loaders() {
+    let elf = token_program_elf();
+    let payer = Pubkey::new_unique();
+    let programs = [
+        (
+            Pubkey::new_unique(),
+            program_account(&bpf_loader_deprecated::id(), &elf),
+        ),
+        (
+            Pubkey::new_unique(),
+            program_account(&bpf_loader::id(), &elf),
+        ),
+        (
+            Pubkey::new_unique(),
+            loader_v4_account(LoaderV4Status::Deployed, &elf),
+        ),
+        (
+            Pubkey::new_unique(),
+            loader_v4_account(LoaderV4Status::Finalized, &elf),
+        ),
+    ];
+    let simulator = Simulator::new_with_accounts(
+        [(payer, system_account(10_000_000_000))]
+            .into_iter()
+            .chain(programs.clone())
+            .collect(),
+    );
+
+    for (program_id, _) in programs {
+        let result = simulator.simulate_transaction_unchecked(&invoke(program_id, &payer), false);
+        assert!(ran(&result.logs, &program_id), "{:?}", result.logs);
+        assert!(result.program_diagnostics.is_empty());
+    }
+}
+
+#[test]
+fn test_loader_v4_disabled() {
+    let payer = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let simulator = Simulator::try_new_from_accounts(
+        SimulatorConfig {
+            feature_set: FeatureSetConfig::AllEnabledExcept(vec![enable_loader_v4::id()]),
+            ..SimulatorConfig::default()
+        },
+        vec![
+            (payer, system_account(10_000_000_000)),
+            (
+                program_id,
+                loader_v4_account(LoaderV4Status::Deployed, &token_program_elf()),
+            ),
+        ],
+    )
+    .unwrap();
+
+    let result = simulator.simulate_transaction_unchecked(&invoke(program_id, &payer), false);
+    assert!(result.result.is_err());
+    assert!(!ran(&result.logs, &program_id), "{:?}", result.logs);
+}
+
+#[test]
+fn test_loader_v4_retracted() {
+    let elf = token_program_elf();
+    let payer = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let simulator = Simulator::new_with_accounts(vec![
+        (payer, system_account(10_000_000_000)),
+        (
+            program_id,
+            loader_v4_account(LoaderV4Status::Deployed, &elf),
+        ),
+    ]);
+    let transaction = invoke(program_id, &payer);
+    let overrides = HashMap::from([(
+        program_id,
+        loader_v4_account(LoaderV4Status::Retracted, &elf),
+    )]);
+
+    let result =
+        simulator.simulate_transaction_unchecked_with_overrides(&transaction, &overrides, false);
+    assert!(result.result.is_err());
+    assert!(!ran(&result.logs, &program_id), "{:?}", result.logs);
+    assert_eq!(result.program_diagnostics.len(), 1);
+    assert_eq!(result.program_diagnostics[0].program_id, program_id);
+
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert!(ran(&result.logs, &program_id), "{:?}", result.logs);
+    assert!(result.program_diagnostics.is_empty());
+}
+
+#[test]
+fn test_loader_v4_malformed() {
+    let payer = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let simulator = Simulator::new_with_accounts(vec![
+        (payer, system_account(10_000_000_000)),
+        (program_id, program_account(&loader_v4::id(), &[0; 10])),
+    ]);
+
+    let result = simulator.simulate_transaction_unchecked(&invoke(program_id, &payer), false);
+    assert_eq!(
+        result.result,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::UnsupportedProgramId
+        ))
+    );
+    assert_eq!(result.program_diagnostics.len(), 1);
+    assert_eq!(result.program_diagnostics[0].program_id, program_id);
+}
@@ -0,0 +1,158 @@
+use {
+    solana_address_lookup_table_interface::{
+        program as address_lookup_table_program,
+        state::{AddressLookupTable, LookupTableMeta},
+    },
+    solana_sdk::{
+        account::{create_account_shared_data_with_fields, AccountSharedData},
+        clock::{Clock, Slot},
+        hash::Hash,
+        message::{v0, AddressLookupTableAccount, VersionedMessage},
+        pubkey::Pubkey,
+        signature::Signature,
+        system_instruction, system_program,
+        sysvar::SysvarId,
+        transaction::{MessageHash, SanitizedTransaction, VersionedTransaction},
+    },
+    solana_simulate::{ClockConfig, Simulator, SimulatorConfig, SimulatorError},
+    std::{borrow::Cow, collections::HashSet},
+};
+
+// Lookup tables fetched from mainnet were last extended at a recent slot.
+const LAST_EXTENDED_SLOT: Slot = 300_000_000;
+
+fn lookup_table_account(addresses: &[Pubkey]) -> AccountSharedData {
+    let lookup_table = AddressLookupTable {
+        meta: LookupTableMeta {
+            deactivation_slot: Slot::MAX,
+            last_extended_slot: LAST_EXTENDED_SLOT,
+            last_extended_slot_start_index: 0,
+            authority: Some(Pubkey::new_unique()),
+            _padding: 0,
+        },
+        addresses: Cow::Borrowed(addresses),
+    };
+    let data = lookup_table.serialize_for_tests().unwrap();
+    let mut account =
+        AccountSharedData::new(1_000_000, data.len(), &address_lookup_table_program::id());
+    account.set_data_from_slice(&data);
+    account
+}
+
+struct Setup {
+    accounts: Vec<(Pubkey, AccountSharedData)>,
+    payer: Pubkey,
+    to: Pubkey,
+    table: Pubkey,
+}
+
+fn setup() -> Setup {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let table = Pubkey::new_unique();
+    Setup {
+        accounts: vec![
+            (
+                payer,
+                AccountSharedData::new(10_000_000_000, 0, &system_program::id()),
+            ),
+            (table, lookup_table_account(&[to])),
+        ],
+        payer,
+        to,
+        table,
+    }
+}
+
+fn transfer_through_table(simulator: &Simulator, setup: &Setup) -> SanitizedTransaction {
+    let message = v0::Message::try_compile(
+        &setup.payer,
+        &[system_instruction::transfer(
+            &setup.payer,
+            &setup.to,
+            1_000_000,
+        )],
+        &[AddressLookupTableAccount {
+            key: setup.table,
+            addresses: vec![setup.to],
+        }],
+        Hash::default(),
+    )
+    .unwrap();
+    assert_eq!(message.address_table_lookups.len(), 1);
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::V0(message),
+    };
+    SanitizedTransaction::try_create(
+        transaction,
+        MessageHash::Compute,
+        None,
+        simulator.clone(),
+        &HashSet::new(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_lookup_table_at_clock_sysvar_slot() {
+    let mut setup = setup();
+    let clock = Clock {
+        slot: LAST_EXTENDED_SLOT + 10,
+        ..Clock::default()
+    };
+    setup.accounts.push((
+        Clock::id(),
+        create_account_shared_data_with_fields(&clock, (1, 0)),
+    ));
+    let simulator =
+        Simulator::try_new_from_accounts(SimulatorConfig::default(), setup.accounts.clone())
+            .unwrap();
+    assert_eq!(simulator.clock().slot, LAST_EXTENDED_SLOT + 10);
+
+    let transaction = transfer_through_table(&simulator, &setup);
+    assert_eq!(transaction.message().account_keys().get(2), Some(&setup.to));
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+}
+
+#[test]
+fn test_lookup_table_at_configured_slot() {
+    let setup = setup();
+    let simulator = Simulator::try_new_from_accounts(
+        SimulatorConfig {
+            clock: ClockConfig {
+                slot: Some(LAST_EXTENDED_SLOT + 1),
+                ..ClockConfig::default()
+            },
+            ..SimulatorConfig::default()
+        },
+        setup.accounts.clone(),
+    )
+    .unwrap();
+
+    let transaction = transfer_through_table(&simulator, &setup);
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+}
+
+#[test]
+fn test_lookup_table_extended_after_slot() {
+    let setup = setup();
+    for slot in [None, Some(LAST_EXTENDED_SLOT)] {
+        let result = Simulator::try_new_from_accounts(
+            SimulatorConfig {
+                clock: ClockConfig {
+                    slot,
+                    ..ClockConfig::default()
+                },
+                ..SimulatorConfig::default()
+            },
+            setup.accounts.clone(),
+        );
+        assert!(
+            matches!(result, Err(SimulatorError::InvalidConfig(_))),
+            "slot {slot:?}"
+        );
+    }
+}
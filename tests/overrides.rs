@@ -0,0 +1,61 @@
+mod common;
+
+use {
+    common::{system_account, transaction},
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        pubkey::Pubkey,
+        system_instruction,
+        transaction::TransactionError,
+    },
+    solana_simulate::Simulator,
+    std::collections::HashMap,
+};
+
+#[test]
+fn test_override_applies_to_one_simulation() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = Simulator::new_with_accounts(vec![(payer, system_account(1_000_000))]);
+    let transaction = transaction(
+        &[system_instruction::transfer(&payer, &to, 5_000_000)],
+        &payer,
+    );
+    let overrides = HashMap::from([(payer, system_account(10_000_000))]);
+
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert!(result.result.is_err());
+
+    let result =
+        simulator.simulate_transaction_unchecked_with_overrides(&transaction, &overrides, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert_eq!(result.pre_simulation_accounts[0].1.lamports(), 10_000_000);
+    assert_eq!(
+        result.post_simulation_accounts[0].1.lamports(),
+        10_000_000 - 5_000_000 - 5000
+    );
+
+    // The simulator's accounts are untouched.
+    assert_eq!(simulator.get_account(&payer).unwrap().lamports(), 1_000_000);
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert!(result.result.is_err());
+    assert_eq!(result.pre_simulation_accounts[0].1.lamports(), 1_000_000);
+}
+
+#[test]
+fn test_default_override_removes_account() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = Simulator::new_with_accounts(vec![(payer, system_account(10_000_000))]);
+    let transaction = transaction(
+        &[system_instruction::transfer(&payer, &to, 1_000_000)],
+        &payer,
+    );
+    let overrides = HashMap::from([(payer, AccountSharedData::default())]);
+
+    let result =
+        simulator.simulate_transaction_unchecked_with_overrides(&transaction, &overrides, false);
+    assert_eq!(result.result, Err(TransactionError::AccountNotFound));
+    let result = simulator.simulate_transaction_unchecked(&transaction, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use {
+    common::{accounts_file_simulator, swap_transaction, system_account, transaction, RAYDIUM_AMM},
+    solana_sdk::{
+        account::{AccountSharedData, WritableAccount},
+        bpf_loader,
+        instruction::{Instruction, InstructionError},
+        pubkey::Pubkey,
+        transaction::TransactionError,
+    },
+    solana_simulate::Simulator,
+    std::collections::HashMap,
+};
+
+#[test]
+fn test_repeated_simulations() {
+    let simulator = accounts_file_simulator();
+    let transaction = swap_transaction(&simulator);
+
+    let first = simulator.simulate_transaction_unchecked(&transaction, true);
+    assert_eq!(first.result, Ok(()), "{:?}", first.logs);
+    for _ in 0..2 {
+        let result = simulator.simulate_transaction_unchecked(&transaction, true);
+        assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+        assert_eq!(result.logs, first.logs);
+        assert_eq!(result.units_consumed, first.units_consumed);
+        assert_eq!(
+            result.post_simulation_accounts,
+            first.post_simulation_accounts
+        );
+    }
+}
+
+#[test]
+fn test_program_override_does_not_leak() {
+    let simulator = accounts_file_simulator();
+    let transaction = swap_transaction(&simulator);
+    let program = simulator.get_upgradeable_program(&RAYDIUM_AMM).unwrap();
+    // Closing the programdata account makes the AMM unusable in overridden
+    // simulations only.
+    let overrides = HashMap::from([(program.programdata_address, AccountSharedData::default())]);
+
+    std::thread::scope(|scope| {
+        for thread in 0..4 {
+            let (simulator, transaction, overrides) = (&simulator, &transaction, &overrides);
+            scope.spawn(move || {
+                for i in 0..6 {
+                    if (i + thread) % 2 == 0 {
+                        let result = simulator.simulate_transaction_unchecked_with_overrides(
+                            transaction,
+                            overrides,
+                            false,
+                        );
+                        assert!(result.result.is_err());
+                        assert_eq!(result.program_diagnostics.len(), 1);
+                    } else {
+                        let result = simulator.simulate_transaction_unchecked(transaction, false);
+                        assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+                        assert!(result.program_diagnostics.is_empty());
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn test_program_failing_verification() {
+    let payer = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let mut program = AccountSharedData::new(1_000_000_000, 64, &bpf_loader::id());
+    program.set_executable(true);
+    // Programs that fail verification don't prevent the simulator from starting.
+    let simulator = Simulator::try_new_with_accounts(vec![
+        (payer, system_account(10_000_000_000)),
+        (program_id, program),
+    ])
+    .unwrap();
+    let transaction = transaction(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        &payer,
+    );
+
+    for _ in 0..2 {
+        let result = simulator.simulate_transaction_unchecked(&transaction, false);
+        assert_eq!(
+            result.result,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::UnsupportedProgramId
+            ))
+        );
+        assert_eq!(result.program_diagnostics.len(), 1);
+        assert_eq!(result.program_diagnostics[0].program_id, program_id);
+    }
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use {
+    common::{system_account, transaction},
+    solana_sdk::{
+        account::ReadableAccount, pubkey::Pubkey, system_instruction, transaction::TransactionError,
+    },
+    solana_simulate::Simulator,
+};
+
+const PAYER_LAMPORTS: u64 = 10_000_000_000;
+const FEE: u64 = 5000;
+
+fn simulator(payer: &Pubkey) -> Simulator {
+    Simulator::new_with_accounts(vec![(*payer, system_account(PAYER_LAMPORTS))])
+}
+
+#[test]
+fn test_process_transaction_commits_state() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = simulator(&payer);
+    let mut session = simulator.session();
+
+    for _ in 0..3 {
+        let result = session.process_transaction(
+            &transaction(
+                &[system_instruction::transfer(&payer, &to, 1_000_000)],
+                &payer,
+            ),
+            false,
+        );
+        assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    }
+    assert_eq!(session.get_account(&to).unwrap().lamports(), 3_000_000);
+    assert_eq!(
+        session.get_account(&payer).unwrap().lamports(),
+        PAYER_LAMPORTS - 3 * (1_000_000 + FEE)
+    );
+
+    // Emptying an account closes it.
+    let result = session.process_transaction(
+        &transaction(
+            &[system_instruction::transfer(&to, &payer, 3_000_000 - FEE)],
+            &to,
+        ),
+        false,
+    );
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert!(session.get_account(&to).is_none());
+
+    // The simulator's accounts are untouched.
+    assert!(simulator.get_account(&to).is_none());
+    assert_eq!(
+        simulator.get_account(&payer).unwrap().lamports(),
+        PAYER_LAMPORTS
+    );
+}
+
+#[test]
+fn test_failed_transaction_commits_fee_payer() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = simulator(&payer);
+    let mut session = simulator.session();
+
+    let result = session.process_transaction(
+        &transaction(
+            &[
+                system_instruction::transfer(&payer, &to, 1_000_000),
+                system_instruction::transfer(&payer, &to, PAYER_LAMPORTS),
+            ],
+            &payer,
+        ),
+        false,
+    );
+    assert!(result.result.is_err());
+    assert_eq!(
+        session.committed_accounts().keys().collect::<Vec<_>>(),
+        vec![&payer]
+    );
+    assert_eq!(
+        session.get_account(&payer).unwrap().lamports(),
+        PAYER_LAMPORTS - FEE
+    );
+    assert!(session.get_account(&to).is_none());
+}
+
+#[test]
+fn test_simulate_transaction_does_not_commit() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = simulator(&payer);
+    let session = simulator.session();
+
+    let result = session.simulate_transaction(
+        &transaction(
+            &[system_instruction::transfer(&payer, &to, 1_000_000)],
+            &payer,
+        ),
+        false,
+    );
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert!(session.committed_accounts().is_empty());
+    assert!(session.get_account(&to).is_none());
+}
+
+#[test]
+fn test_process_bundle() {
+    let payer = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let simulator = simulator(&payer);
+    let mut session = simulator.session();
+    let transfer = transaction(
+        &[system_instruction::transfer(&payer, &to, 1_000_000)],
+        &payer,
+    );
+    let overdraft = transaction(
+        &[system_instruction::transfer(
+            &payer,
+            &to,
+            2 * PAYER_LAMPORTS,
+        )],
+        &payer,
+    );
+
+    // A failing transaction rolls back the whole bundle, fees included.
+    let result = session.process_bundle(
+        &[transfer.clone(), transfer.clone(), overdraft.clone()],
+        false,
+    );
+    assert!(matches!(
+        result.result,
+        Err((2, TransactionError::InstructionError(0, _)))
+    ));
+    assert_eq!(result.transaction_results.len(), 3);
+    assert_eq!(
+        result.transaction_results[1].pre_simulation_accounts[1]
+            .1
+            .lamports(),
+        1_000_000
+    );
+    assert!(session.committed_accounts().is_empty());
+    assert!(session.get_account(&to).is_none());
+
+    // Execution stops at the first failure.
+    let result = session.process_bundle(&[overdraft, transfer.clone()], false);
+    assert!(matches!(result.result, Err((0, _))));
+    assert_eq!(result.transaction_results.len(), 1);
+
+    let result = session.process_bundle(&[transfer.clone(), transfer], false);
+    assert_eq!(result.result, Ok(()));
+    assert_eq!(session.get_account(&to).unwrap().lamports(), 2_000_000);
+    assert_eq!(
+        session.get_account(&payer).unwrap().lamports(),
+        PAYER_LAMPORTS - 2 * (1_000_000 + FEE)
+    );
+
+    let result = simulator.simulate_bundle(
+        &[transaction(
+            &[system_instruction::transfer(&payer, &to, 1_000_000)],
+            &payer,
+        )],
+        false,
+    );
+    assert_eq!(result.result, Ok(()));
+    assert!(simulator.get_account(&to).is_none());
+}
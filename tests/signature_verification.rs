@@ -0,0 +1,44 @@
+mod common;
+
+use {
+    common::{system_account, transaction},
+    solana_sdk::{
+        hash::Hash,
+        message::Message,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::{SanitizedTransaction, Transaction, TransactionError},
+    },
+    solana_simulate::Simulator,
+    std::collections::HashSet,
+};
+
+#[test]
+fn test_simulate_transaction_verifies_signatures() {
+    let keypair = Keypair::new();
+    let payer = keypair.pubkey();
+    let to = Pubkey::new_unique();
+    let simulator = Simulator::new_with_accounts(vec![(payer, system_account(10_000_000_000))]);
+    let instructions = [system_instruction::transfer(&payer, &to, 1_000_000)];
+
+    let unsigned = transaction(&instructions, &payer);
+    let result = simulator.simulate_transaction(&unsigned, false);
+    assert_eq!(result.result, Err(TransactionError::SignatureFailure));
+    assert!(result.logs.is_empty());
+    // Unchecked simulations skip signature verification.
+    let result = simulator.simulate_transaction_unchecked(&unsigned, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+
+    let signed = SanitizedTransaction::try_from_legacy_transaction(
+        Transaction::new(
+            &[&keypair],
+            Message::new(&instructions, Some(&payer)),
+            Hash::default(),
+        ),
+        &HashSet::new(),
+    )
+    .unwrap();
+    let result = simulator.simulate_transaction(&signed, false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+}
@@ -0,0 +1,186 @@
+use {
+    solana_nonce::{
+        state::{DurableNonce, State},
+        versions::Versions,
+    },
+    solana_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        account_utils::StateMut,
+        hash::Hash,
+        instruction::Instruction,
+        message::Message,
+        pubkey::Pubkey,
+        system_instruction, system_program,
+        transaction::{SanitizedTransaction, Transaction, TransactionError},
+    },
+    solana_simulate::{Simulator, SimulatorConfig},
+    std::collections::HashSet,
+};
+
+fn transaction(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    blockhash: Hash,
+) -> SanitizedTransaction {
+    let message = Message::new_with_blockhash(instructions, Some(payer), &blockhash);
+    SanitizedTransaction::try_from_legacy_transaction(
+        Transaction::new_unsigned(message),
+        &HashSet::new(),
+    )
+    .unwrap()
+}
+
+fn nonce_account(authority: &Pubkey, durable_nonce: DurableNonce) -> AccountSharedData {
+    let mut account = AccountSharedData::new(10_000_000, State::size(), &system_program::id());
+    account
+        .set_state(&Versions::new(State::new_initialized(
+            authority,
+            durable_nonce,
+            5000,
+        )))
+        .unwrap();
+    account
+}
+
+fn nonce_blockhash(account: &AccountSharedData) -> Hash {
+    let versions: Versions = account.state().unwrap();
+    match versions.state() {
+        State::Initialized(data) => data.blockhash(),
+        State::Uninitialized => panic!("uninitialized nonce account"),
+    }
+}
+
+struct Setup {
+    simulator: Simulator,
+    payer: Pubkey,
+    nonce: Pubkey,
+    latest_blockhash: Hash,
+    recent_blockhashes: Vec<Hash>,
+    durable_nonce: DurableNonce,
+}
+
+fn setup() -> Setup {
+    let payer = Pubkey::new_unique();
+    let nonce = Pubkey::new_unique();
+    let latest_blockhash = Hash::new_unique();
+    let recent_blockhashes = (0..150).map(|_| Hash::new_unique()).collect::<Vec<_>>();
+    let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+    let simulator = Simulator::try_new_from_accounts(
+        SimulatorConfig {
+            blockhash: latest_blockhash,
+            recent_blockhashes: recent_blockhashes.clone(),
+            check_transaction_age: true,
+            ..Default::default()
+        },
+        vec![
+            (
+                payer,
+                AccountSharedData::new(10_000_000_000, 0, &system_program::id()),
+            ),
+            (nonce, nonce_account(&payer, durable_nonce)),
+        ],
+    )
+    .unwrap();
+    Setup {
+        simulator,
+        payer,
+        nonce,
+        latest_blockhash,
+        recent_blockhashes,
+        durable_nonce,
+    }
+}
+
+#[test]
+fn test_transaction_age() {
+    let setup = setup();
+    let transfer = [system_instruction::transfer(
+        &setup.payer,
+        &Pubkey::new_unique(),
+        1_000_000,
+    )];
+    let simulate = |blockhash| {
+        setup
+            .simulator
+            .simulate_transaction_unchecked(&transaction(&transfer, &setup.payer, blockhash), false)
+            .result
+    };
+
+    assert_eq!(simulate(setup.latest_blockhash), Ok(()));
+    // `MAX_PROCESSING_AGE - MAX_TRANSACTION_FORWARDING_DELAY` blockhashes back.
+    assert_eq!(simulate(setup.recent_blockhashes[143]), Ok(()));
+    assert_eq!(
+        simulate(setup.recent_blockhashes[144]),
+        Err(TransactionError::BlockhashNotFound)
+    );
+    assert_eq!(
+        simulate(Hash::new_unique()),
+        Err(TransactionError::BlockhashNotFound)
+    );
+
+    let unknown = transaction(&transfer, &setup.payer, Hash::new_unique());
+    let replaced = setup.simulator.replace_recent_blockhash(&unknown).unwrap();
+    assert_eq!(
+        replaced.message().recent_blockhash(),
+        &setup.latest_blockhash
+    );
+    assert_eq!(
+        setup
+            .simulator
+            .simulate_transaction_unchecked(&replaced, false)
+            .result,
+        Ok(())
+    );
+}
+
+#[test]
+fn test_durable_nonce() {
+    let setup = setup();
+    let nonce_transaction = |authority: &Pubkey, lamports| {
+        transaction(
+            &[
+                system_instruction::advance_nonce_account(&setup.nonce, authority),
+                system_instruction::transfer(&setup.payer, &Pubkey::new_unique(), lamports),
+            ],
+            &setup.payer,
+            *setup.durable_nonce.as_hash(),
+        )
+    };
+
+    let mut session = setup.simulator.session();
+    let result = session.process_transaction(&nonce_transaction(&setup.payer, 1_000_000), false);
+    assert_eq!(result.result, Ok(()), "{:?}", result.logs);
+    assert_eq!(
+        nonce_blockhash(&session.get_account(&setup.nonce).unwrap()),
+        *DurableNonce::from_blockhash(&setup.latest_blockhash).as_hash()
+    );
+    // The nonce was advanced, so it can't be used again.
+    assert_eq!(
+        session
+            .process_transaction(&nonce_transaction(&setup.payer, 1_000_000), false)
+            .result,
+        Err(TransactionError::BlockhashNotFound)
+    );
+
+    // Failed transactions still advance the nonce.
+    let mut session = setup.simulator.session();
+    let result =
+        session.process_transaction(&nonce_transaction(&setup.payer, 100_000_000_000), false);
+    assert!(result.result.is_err());
+    assert_ne!(
+        session.get_account(&setup.nonce).unwrap().data(),
+        setup.simulator.get_account(&setup.nonce).unwrap().data()
+    );
+
+    // The nonce authority must sign.
+    assert_eq!(
+        setup
+            .simulator
+            .simulate_transaction_unchecked(
+                &nonce_transaction(&Pubkey::new_unique(), 1_000_000),
+                false
+            )
+            .result,
+        Err(TransactionError::BlockhashNotFound)
+    );
+}
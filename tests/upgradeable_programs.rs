@@ -0,0 +1,79 @@
+mod common;
+
+use {
+    common::{
+        accounts_file_simulator, swap_transaction, system_account, token_program_elf, transaction,
+        upgradeable_program_accounts, RAYDIUM_AMM, TOKEN_PROGRAM,
+    },
+    solana_sdk::{
+        account::AccountSharedData,
+        bpf_loader_upgradeable::{self, get_program_data_address, UpgradeableLoaderState},
+        instruction::Instruction,
+        pubkey::Pubkey,
+    },
+    solana_simulate::Simulator,
+    std::collections::HashMap,
+};
+
+#[test]
+fn test_get_upgradeable_program() {
+    let simulator = accounts_file_simulator();
+
+    let program = simulator.get_upgradeable_program(&RAYDIUM_AMM).unwrap();
+    assert_eq!(
+        program.programdata_address,
+        get_program_data_address(&RAYDIUM_AMM)
+    );
+    assert!(program.deployment_slot > 0);
+    assert!(program.upgrade_authority.is_some());
+
+    let diagnostic = simulator
+        .get_upgradeable_program(&TOKEN_PROGRAM)
+        .unwrap_err();
+    assert_eq!(diagnostic.program_id, TOKEN_PROGRAM);
+}
+
+#[test]
+fn test_closed_programdata() {
+    let simulator = accounts_file_simulator();
+    let transaction = swap_transaction(&simulator);
+    // Closing a program leaves an uninitialized programdata account behind.
+    let closed = AccountSharedData::new_data(
+        1_000_000,
+        &UpgradeableLoaderState::Uninitialized,
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    let overrides = HashMap::from([(get_program_data_address(&RAYDIUM_AMM), closed)]);
+
+    let result =
+        simulator.simulate_transaction_unchecked_with_overrides(&transaction, &overrides, false);
+    assert!(result.result.is_err());
+    assert_eq!(result.program_diagnostics.len(), 1);
+    assert_eq!(result.program_diagnostics[0].program_id, RAYDIUM_AMM);
+    assert!(result.program_diagnostics[0].reason.contains("closed"));
+}
+
+#[test]
+fn test_missing_programdata() {
+    let payer = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let [program, _programdata] = upgradeable_program_accounts(program_id, &token_program_elf());
+    // The simulator starts without the programdata account.
+    let simulator =
+        Simulator::try_new_with_accounts(vec![(payer, system_account(10_000_000_000)), program])
+            .unwrap();
+
+    let result = simulator.simulate_transaction_unchecked(
+        &transaction(
+            &[Instruction::new_with_bytes(program_id, &[], vec![])],
+            &payer,
+        ),
+        false,
+    );
+    assert!(result.result.is_err());
+    assert_eq!(result.program_diagnostics.len(), 1);
+    assert_eq!(result.program_diagnostics[0].program_id, program_id);
+    assert!(result.program_diagnostics[0].reason.contains("not found"));
+    assert!(simulator.get_upgradeable_program(&program_id).is_err());
+}